@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::error::{Error, Result};
+
+/// One cached page of a Canvas response.
+#[derive(Serialize, Deserialize)]
+pub struct CachedResponse {
+    /// The url the page was fetched from, with its query.
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+    /// The `rel="next"` link, for reading every page back without Canvas.
+    pub next_url: Option<String>,
+    pub body: Value,
+}
+
+/// Canvas responses stored on disk, one file per page url, so they can be
+/// revalidated with `If-None-Match`/`If-Modified-Since` or read offline.
+pub struct ResponseCache {
+    dir: PathBuf,
+    // whose responses these are, two logins to the same canvas see different things.
+    credential: String,
+}
+
+impl ResponseCache {
+    /// A cache in `dir`, which is created when the first response is stored.
+    pub fn new(dir: impl Into<PathBuf>) -> ResponseCache {
+        ResponseCache { dir: dir.into(), credential: String::new() }
+    }
+
+    /// Keeps the responses for the token stored under `name` apart from other logins' to the same Canvas.
+    pub fn for_credential(mut self, name: &str) -> ResponseCache {
+        self.credential = name.to_string();
+        self
+    }
+
+    /// The cache in `$XDG_CACHE_HOME/maker`.
+    pub fn open_default() -> Result<ResponseCache> {
+        get_cache_dir()
+            .map(ResponseCache::new)
+            .ok_or_else(|| Error::Config(String::from("Can't find the cache directory, `$HOME` isn't set")))
+    }
+
+    /// The directory the responses are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // the file name is a hash, urls have characters that aren't allowed in one.
+    fn get_file(&self, url: &str) -> PathBuf {
+        let key: String = format!("{}\n{url}", self.credential);
+        let hash: String = Sha256::digest(key.as_bytes()).iter().map(|x| format!("{x:02x}")).collect();
+        self.dir.join(format!("{hash}.json"))
+    }
+
+    /// The cached page for `url`, `None` if it isn't cached or can't be read.
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let contents: String = fs::read_to_string(self.get_file(url)).ok()?;
+        serde_json::from_str::<CachedResponse>(&contents).ok().filter(|x| x.url == url)
+    }
+
+    /// Stores a page, replacing what was cached for its url.
+    pub fn put(&self, response: &CachedResponse) -> Result<()> {
+        fs::create_dir_all(&self.dir).map_err(|error| Error::io(&self.dir, error))?;
+        let path: PathBuf = self.get_file(&response.url);
+        let contents: String = serde_json::to_string(response).map_err(|error| Error::json("the cached response", error))?;
+        fs::write(&path, contents).map_err(|error| Error::io(path, error))
+    }
+
+    /// Deletes every cached page, returning how many there were.
+    pub fn clear(&self) -> Result<usize> {
+        let entries: fs::ReadDir = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(error) => return Err(Error::io(&self.dir, error)),
+        };
+        let mut count: usize = 0;
+        for entry in entries {
+            let path: PathBuf = entry.map_err(|error| Error::io(&self.dir, error))?.path();
+            // only what the cache wrote, in case the directory is shared.
+            if path.extension().is_some_and(|x| x == "json") {
+                fs::remove_file(&path).map_err(|error| Error::io(&path, error))?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+// `$XDG_CACHE_HOME/maker`, which defaults to `~/.cache/maker`.
+fn get_cache_dir() -> Option<PathBuf> {
+    let dir: PathBuf = match std::env::var_os("XDG_CACHE_HOME").filter(|x| !x.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => config::get_home_dir().ok().map(|x| Path::new(&x).join(".cache"))?,
+    };
+    Some(dir.join("maker"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_response(url: &str, body: Value) -> CachedResponse {
+        CachedResponse { url: url.to_string(), etag: None, last_modified: None, fetched_at: Utc::now(), next_url: None, body }
+    }
+
+    #[test]
+    fn credentials_dont_share_responses() {
+        let dir: PathBuf = std::env::temp_dir().join(format!("maker-cache-test-{}", std::process::id()));
+        let url: &str = "https://canvas.example.edu/api/v1/courses";
+        let alice: ResponseCache = ResponseCache::new(&dir).for_credential("alice");
+        let bob: ResponseCache = ResponseCache::new(&dir).for_credential("bob");
+
+        alice.put(&get_response(url, Value::from("alice's courses"))).unwrap();
+        assert!(bob.get(url).is_none());
+        bob.put(&get_response(url, Value::from("bob's courses"))).unwrap();
+        assert_eq!(alice.get(url).unwrap().body, "alice's courses");
+        assert_eq!(bob.get(url).unwrap().body, "bob's courses");
+
+        assert_eq!(alice.clear().unwrap(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+use chrono::Duration;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use maker::SubmissionType;
+use std::path::PathBuf;
+
+const EXIT_CODE_HELP: &str = "Exit codes:
+  0    success
+  2    invalid arguments
+  3    missing or invalid config
+  4    canvas request failed
+  5    canvas didn't have what was asked for
+  6    filesystem error
+  130  cancelled";
+
+/// Makes markdown lab notes from Canvas assignments.
+///
+/// Run without a command to pick what to do from a menu.
+///
+/// The config is read from `--config`, `$MAKER_CONFIG`, `./local.json`, or
+/// `$XDG_CONFIG_HOME/maker/config.json`, whichever is found first.
+#[derive(Parser)]
+#[command(name = "maker", version, after_help = EXIT_CODE_HELP)]
+pub struct Cli {
+    /// Answer yes to every confirmation (creating directories, merging into existing notes, submitting).
+    #[arg(short, long, global = true)]
+    pub yes: bool,
+
+    /// The config file to use, instead of looking for one.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// The profile in the config file to use.
+    #[arg(short, long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Only list courses in terms with this in the name, like `Fall 2026`.
+    #[arg(long, global = true, value_name = "NAME")]
+    pub term: Option<String>,
+
+    /// Also list completed courses.
+    #[arg(long, global = true)]
+    pub all_courses: bool,
+
+    /// Use only what's cached, without asking Canvas.
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Create a lab note for an assignment.
+    New(NewArgs),
+    /// Submit a lab note to Canvas.
+    Submit(SubmitArgs),
+    /// List courses or assignments.
+    #[command(subcommand)]
+    List(ListCommand),
+    /// Store or check the Canvas token.
+    #[command(subcommand)]
+    Auth(AuthCommand),
+    /// Create lab notes for new assignments and report changed ones.
+    Sync(SyncArgs),
+    /// Show every lab's due date, note, unanswered questions, submission and grade.
+    Status(StatusArgs),
+    /// Manage the cached Canvas responses.
+    #[command(subcommand)]
+    Cache(CacheCommand),
+}
+
+/// Picks a course and an assignment, prompting for whichever is missing.
+#[derive(Args, Default)]
+pub struct Selection {
+    /// The course id or name.
+    #[arg(short, long)]
+    pub course: Option<String>,
+
+    /// The assignment id or name.
+    #[arg(short, long)]
+    pub assignment: Option<String>,
+
+    #[command(flatten)]
+    pub filter: AssignmentFilter,
+}
+
+/// Narrows down the assignments to pick from.
+#[derive(Args, Default)]
+pub struct AssignmentFilter {
+    /// Only assignments you haven't submitted.
+    #[arg(long)]
+    pub unsubmitted: bool,
+
+    /// Only assignments due within a time, like `7d`, `12h` or `2w`.
+    #[arg(long, value_name = "TIME", value_parser = parse_duration)]
+    pub due_within: Option<Duration>,
+
+    /// Only assignments without a lab note yet.
+    #[arg(long)]
+    pub missing_note: bool,
+
+    /// Every assignment in the course, not just the ones in the lab groups.
+    #[arg(long)]
+    pub all_groups: bool,
+}
+
+// a number with a unit, `30m`, `12h`, `7d` or `2w`.
+fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text: &str = text.trim();
+    let split: usize = text.find(|x: char| !x.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: i64 = number.parse().map_err(|_| format!("`{text}` doesn't start with a number"))?;
+    let duration: Option<Duration> = match unit {
+        "m" => Duration::try_minutes(number),
+        "h" => Duration::try_hours(number),
+        "d" | "" => Duration::try_days(number),
+        "w" => Duration::try_weeks(number),
+        _ => return Err(format!("unknown unit `{unit}`, use m, h, d or w")),
+    };
+    match duration {
+        Some(duration) if duration > Duration::zero() => Ok(duration),
+        Some(_) => Err(String::from("it must be more than 0")),
+        None => Err(format!("`{text}` is too long")),
+    }
+}
+
+#[derive(Args, Default)]
+pub struct NewArgs {
+    #[command(flatten)]
+    pub selection: Selection,
+
+    /// Don't open the lab note in an editor.
+    #[arg(long)]
+    pub no_edit: bool,
+
+    /// Print the lab note instead of writing it.
+    #[arg(long)]
+    pub stdout: bool,
+
+    /// Include the whole assignment description, not just the questions.
+    #[arg(long)]
+    pub full: bool,
+
+    /// If the lab note already exists, merge the new questions into it and keep the answers.
+    #[arg(long)]
+    pub merge: bool,
+
+    /// If the lab note already exists, replace it, answers and all.
+    #[arg(long, conflicts_with_all = ["merge", "all"])]
+    pub overwrite: bool,
+
+    /// Create the lab notes for every assignment in the course that doesn't have one yet.
+    #[arg(long, conflicts_with_all = ["assignment", "stdout"])]
+    pub all: bool,
+}
+
+#[derive(Args)]
+pub struct SyncArgs {
+    /// Only this course, by id or name.
+    #[arg(short, long)]
+    pub course: Option<String>,
+
+    /// Keep checking, every `--interval`.
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// How long to wait between checks, like `15m` or `1h`.
+    #[arg(long, value_name = "TIME", value_parser = parse_duration, default_value = "15m")]
+    pub interval: Duration,
+
+    /// Show a desktop notification for new or changed assignments.
+    #[arg(long)]
+    pub notify: bool,
+
+    /// A command to run for each new or changed assignment, replacing `sync.hook`.
+    #[arg(long, value_name = "COMMAND")]
+    pub hook: Option<String>,
+}
+
+#[derive(Args)]
+pub struct StatusArgs {
+    /// Only this course, by id or name.
+    #[arg(short, long)]
+    pub course: Option<String>,
+
+    /// How to print the status.
+    #[arg(short, long, value_enum, default_value = "table")]
+    pub format: OutputFormat,
+
+    #[command(flatten)]
+    pub filter: AssignmentFilter,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns for the terminal.
+    Table,
+    /// A JSON array, one object per assignment.
+    Json,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SubmitType {
+    /// Submit the note as a text entry.
+    Text,
+    /// Upload the note as a file.
+    Upload,
+}
+
+impl From<SubmitType> for SubmissionType {
+    fn from(submit_type: SubmitType) -> SubmissionType {
+        match submit_type {
+            SubmitType::Text => SubmissionType::OnlineTextEntry,
+            SubmitType::Upload => SubmissionType::OnlineUpload,
+        }
+    }
+}
+
+#[derive(Args, Default)]
+pub struct SubmitArgs {
+    #[command(flatten)]
+    pub selection: Selection,
+
+    /// How to submit the note, prompts if the assignment accepts both.
+    #[arg(short = 't', long = "type", value_enum)]
+    pub submit_type: Option<SubmitType>,
+}
+
+#[derive(Subcommand)]
+pub enum AuthCommand {
+    /// Check a token with Canvas (or log in with OAuth) and store it in the keyring, or an encrypted file if there isn't one.
+    Login {
+        /// The name to store the token under, defaults to the Canvas host.
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Store the token in the encrypted file even if there is a keyring.
+        #[arg(long)]
+        file: bool,
+
+        /// Read the token from stdin instead of prompting for it.
+        #[arg(long)]
+        token_stdin: bool,
+
+        /// Log in through the browser with the developer key in the config's `oauth`, instead of pasting a token.
+        #[arg(long, conflicts_with = "token_stdin")]
+        oauth: bool,
+    },
+    /// Show where the token comes from and check it with Canvas.
+    Status,
+}
+
+#[derive(Subcommand)]
+pub enum ListCommand {
+    /// List your courses.
+    Courses,
+    /// List the assignments in a course.
+    Assignments {
+        /// The course id or name.
+        #[arg(short, long)]
+        course: Option<String>,
+
+        #[command(flatten)]
+        filter: AssignmentFilter,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Delete every cached response.
+    Clear,
+    /// Fetch the courses and their assignments, so lab notes can be made with `--offline`.
+    Sync {
+        /// Only this course, by id or name.
+        #[arg(short, long)]
+        course: Option<String>,
+    },
+}
@@ -0,0 +1,701 @@
+use serde::Deserialize;
+use regex::{Regex, RegexBuilder};
+use reqwest::Url;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::{env, fs};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cache::ResponseCache;
+use crate::credentials::{self, CredentialStore};
+use crate::error::{Error, Result};
+use crate::naming::{self, NamingPattern};
+use crate::oauth::{self, OAuthConfig, OAuthSession, OAuthTokens};
+use crate::parser::{self, QuestionRules};
+use crate::requester::{self, CanvasClient, Course, RetryPolicy};
+
+/// The Canvas instance used when `local.json` doesn't set one.
+pub const DEFAULT_CANVAS_URL: &str = "https://canvas.cse.taylor.edu";
+
+/// The config file looked for in the current directory.
+pub const LOCAL_CONFIG_FILE: &str = "local.json";
+/// The environment variable with the config file's path.
+pub const CONFIG_VAR: &str = "MAKER_CONFIG";
+/// The environment variable with the profile to use.
+pub const PROFILE_VAR: &str = "MAKER_PROFILE";
+/// The environment variables that override a config setting, and the setting.
+pub const ENV_OVERRIDES: [(&str, &str); 8] = [
+    ("MAKER_NAME", "name"),
+    ("MAKER_TOKEN", "token"),
+    ("MAKER_BASE_DIR", "base_dir"),
+    ("MAKER_CANVAS_URL", "canvas_url"),
+    ("MAKER_TEMPLATE", "template"),
+    ("MAKER_NAMING_PATTERN", "naming_pattern"),
+    ("MAKER_EDITOR", "editor"),
+    ("MAKER_TERM", "term"),
+];
+
+/// The editor used when neither `editor`, `$VISUAL`, nor `$EDITOR` is set.
+pub const FALLBACK_EDITOR: &str = "vi";
+
+fn default_canvas_url() -> String {
+    String::from(DEFAULT_CANVAS_URL)
+}
+
+pub(crate) fn get_home_dir() -> Result<String> {
+    env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .map_err(|_| Error::Config(String::from("Can't expand `~`, `$HOME` isn't set")))
+}
+
+// replaces `$VAR` and `${VAR}` with the variable's value.
+fn expand_variables(path: &str) -> Result<String> {
+    let mut expanded: String = String::with_capacity(path.len());
+    let mut rest: &str = path;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after: &str = &rest[start + 1..];
+        let (name, end): (&str, usize) = if let Some(braced) = after.strip_prefix('{') {
+            let close: usize = braced.find('}')
+                .ok_or_else(|| Error::Config(format!("Invalid path `{path}`: a `${{` is never closed")))?;
+            (&braced[..close], close + 2)
+        } else {
+            let length: usize = after.find(|x: char| !(x.is_ascii_alphanumeric() || x == '_')).unwrap_or(after.len());
+            (&after[..length], length)
+        };
+        if name.is_empty() {
+            // a lone `$` is just a character.
+            expanded.push('$');
+        } else {
+            let value: String = env::var(name)
+                .map_err(|_| Error::Config(format!("Invalid path `{path}`: `${name}` isn't set")))?;
+            expanded.push_str(&value);
+        }
+        rest = &after[end..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Expands `~`, `$VAR` and `${VAR}` in a path from the config. Relative paths
+/// are resolved against `config_dir`, the directory the config file is in.
+pub fn expand_path(path: &Path, config_dir: &Path) -> Result<PathBuf> {
+    let path: String = path.to_string_lossy().to_string();
+    let path: String = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\') => get_home_dir()? + rest,
+        _ => path,
+    };
+    let path: PathBuf = PathBuf::from(expand_variables(&path)?);
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        Ok(config_dir.join(path))
+    }
+}
+
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|x| x.to_string()).collect()
+}
+
+/// How to find the questions in an assignment description, anything left out uses the defaults.
+#[derive(Deserialize, Default)]
+pub struct QuestionRulesConfig {
+    /// The heading tags that can start the questions section, like `["h1", "h2"]`.
+    pub header_tags: Option<Vec<String>>,
+    /// Case-insensitive regexes, one of which the heading text has to match.
+    pub header_patterns: Option<Vec<String>>,
+    /// The lists that hold the questions, `ol` and/or `ul`.
+    pub list_types: Option<Vec<String>>,
+    /// Whether a paragraph of only bold text can be the heading.
+    pub bold_paragraphs: Option<bool>,
+}
+
+/// Settings for a single course.
+#[derive(Deserialize, Default)]
+pub struct CourseConfig {
+    /// Replaces the global question rules for this course, one field at a time.
+    #[serde(default)]
+    pub question_rules: QuestionRulesConfig,
+    /// A lab note template for this course, replacing the global one.
+    pub template: Option<PathBuf>,
+    /// The assignment groups to pick assignments from, replacing the global ones.
+    pub assignment_groups: Option<Vec<String>>,
+    /// Pick from every assignment in this course, not just the ones in `assignment_groups`.
+    pub all_groups: Option<bool>,
+    /// List the course first, whatever its term.
+    #[serde(default)]
+    pub pinned: bool,
+    /// Leave the course out of the course list.
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+/// How requests to Canvas are sent, anything left out uses the defaults.
+#[derive(Deserialize, Default)]
+pub struct RequestConfig {
+    /// Seconds a request can take before it's given up on.
+    pub timeout: Option<f64>,
+    /// How many times a failed GET is tried again.
+    pub max_retries: Option<u32>,
+    /// Seconds to wait before the first retry, doubling each time.
+    pub retry_delay: Option<f64>,
+    /// The longest wait between retries in seconds.
+    pub max_retry_delay: Option<f64>,
+}
+
+/// What `maker sync` does when it finds new or changed assignments.
+#[derive(Deserialize, Default)]
+pub struct SyncConfig {
+    /// Show a desktop notification.
+    #[serde(default)]
+    pub notify: bool,
+    /// A command run once for every new or changed assignment, with the details in `MAKER_*` variables.
+    pub hook: Option<String>,
+}
+
+// seconds from the config, which have to be a positive number.
+fn get_seconds(key: &str, seconds: Option<f64>, default: Duration) -> Result<Duration> {
+    match seconds {
+        Some(seconds) if seconds > 0.0 => Duration::try_from_secs_f64(seconds)
+            .map_err(|_| Error::Config(format!("Invalid `requests.{key}` `{seconds}`: it is too long"))),
+        Some(seconds) => Err(Error::Config(format!("Invalid `requests.{key}` `{seconds}`: it must be more than 0"))),
+        None => Ok(default),
+    }
+}
+
+fn build_group_patterns(patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns.iter()
+        .map(|pattern| RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|error| Error::Config(format!("Invalid `assignment_groups` pattern `{pattern}`: {error}"))))
+        .collect()
+}
+
+/// The user's settings, read from the config file (usually `local.json`).
+#[derive(Deserialize)]
+pub struct LocalData {
+    /// The name written at the top of every lab note.
+    pub name: String,
+    /// A Canvas access token. Better left out, and stored with `maker auth login` instead.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// The name the token is stored under, defaults to the Canvas host.
+    pub credential: Option<String>,
+    /// A developer key, for `maker auth login --oauth`.
+    pub oauth: Option<OAuthConfig>,
+    /// The directory lab notes are stored under. `~` and environment
+    /// variables are expanded, and relative paths start at the config file.
+    pub base_dir: PathBuf,
+    /// The root url of the Canvas instance, without `/api/v1`.
+    #[serde(default = "default_canvas_url")]
+    pub canvas_url: String,
+    /// Convert the whole assignment description into the lab note, not just the questions.
+    #[serde(default)]
+    pub full_description: bool,
+    /// How to find the questions in an assignment description.
+    #[serde(default)]
+    pub question_rules: QuestionRulesConfig,
+    /// Settings for single courses, keyed by course id.
+    #[serde(default)]
+    pub courses: HashMap<String, CourseConfig>,
+    /// Where lab notes go under `base_dir`, see [`NamingPattern`].
+    pub naming_pattern: Option<String>,
+    /// The command that opens a lab note, like `code --wait`. The path is added as the last argument.
+    pub editor: Option<String>,
+    /// A template file for the lab note layout, see [`template::DEFAULT_TEMPLATE`](crate::template::DEFAULT_TEMPLATE).
+    pub template: Option<PathBuf>,
+    /// The names of the assignment groups with the labs, as case-insensitive regexes.
+    pub assignment_groups: Option<Vec<String>>,
+    /// Pick from every assignment in a course, not just the ones in `assignment_groups`.
+    #[serde(default)]
+    pub all_groups: bool,
+    /// Timeouts and retries for requests to Canvas.
+    #[serde(default)]
+    pub requests: RequestConfig,
+    /// Notifications for `maker sync`.
+    #[serde(default)]
+    pub sync: SyncConfig,
+    /// Only list courses whose term name contains this, like `Fall 2026`.
+    pub term: Option<String>,
+    /// Also list completed courses and courses the enrollment has ended in.
+    #[serde(default)]
+    pub all_courses: bool,
+    /// The config file these settings were read from.
+    #[serde(skip)]
+    pub config_file: PathBuf,
+}
+
+impl LocalData {
+    fn get_config_help(local_file: &str) -> String {
+        let retry: RetryPolicy = RetryPolicy::default();
+        format!("Please make sure the `{local_file}` file is formatted like this:
+{{
+    \"name\": \"<your name here>\",
+    \"token\": \"<token from canvas here, or leave it out and run `maker auth login`>\",
+    \"credential\": \"<name of the stored token, defaults to the canvas host>\",
+    \"oauth\": {{
+        \"client_id\": \"<developer key id, to log in with `maker auth login --oauth`>\",
+        \"client_secret\": \"<developer key secret>\",
+        \"redirect_port\": <port for the redirect uri http://127.0.0.1:<port>/oauth/callback, defaults to {}>,
+        \"authorize_url\": \"<defaults to <canvas_url>/login/oauth2/auth>\",
+        \"token_url\": \"<defaults to <canvas_url>/login/oauth2/token>\"
+    }},
+    \"base_dir\": \"<directory for storing lab notes, can use ~ and $VARS, relative to this file>\",
+    \"canvas_url\": \"<canvas instance url, defaults to {DEFAULT_CANVAS_URL}>\",
+    \"full_description\": <true to include the whole assignment description, defaults to false>,
+    \"question_rules\": {{
+        \"header_tags\": <heading tags that start the questions, defaults to {:?}>,
+        \"header_patterns\": <case-insensitive regexes for the heading text, defaults to {:?}>,
+        \"list_types\": <lists that hold the questions, defaults to {:?}>,
+        \"bold_paragraphs\": <true if a bold paragraph can be the heading, defaults to true>
+    }},
+    \"courses\": {{
+        \"<course id>\": {{
+            \"question_rules\": <the same as above, for this course only>,
+            \"template\": <a lab note template for this course only>,
+            \"assignment_groups\": <the same as below, for this course only>,
+            \"all_groups\": <the same as below, for this course only>,
+            \"pinned\": <true to list this course first, even outside `term`>,
+            \"hidden\": <true to leave this course out of the list>
+        }}
+    }},
+    \"template\": <path to a lab note template, optional>,
+    \"naming_pattern\": <lab note path under base_dir, defaults to \"{}\", can use {}>,
+    \"editor\": <command to open lab notes with, defaults to $VISUAL, $EDITOR, then {FALLBACK_EDITOR}>,
+    \"requests\": {{
+        \"timeout\": <seconds a request can take, defaults to {}>,
+        \"max_retries\": <how many times a failed request is tried again, defaults to {}>,
+        \"retry_delay\": <seconds before the first retry, doubling each time, defaults to {}>,
+        \"max_retry_delay\": <the longest wait between retries in seconds, defaults to {}>
+    }},
+    \"sync\": {{
+        \"notify\": <true for a desktop notification when `maker sync` finds new or changed assignments>,
+        \"hook\": \"<command run for each of them, with $MAKER_EVENT, $MAKER_COURSE, $MAKER_ASSIGNMENT, $MAKER_ASSIGNMENT_ID, $MAKER_URL and $MAKER_NOTE set>\"
+    }},
+    \"assignment_groups\": <case-insensitive regexes for the lab group names, defaults to {:?}, asks if none match>,
+    \"all_groups\": <true to pick from every assignment in a course, ignoring groups, defaults to false>,
+    \"term\": \"<only list courses in terms with this in the name, optional>\",
+    \"all_courses\": <true to list completed courses too, defaults to false>,
+    \"profiles\": {{
+        \"<profile name>\": {{ <any of the settings above, replacing the ones outside the profile> }}
+    }},
+    \"default_profile\": <the profile used without --profile or ${PROFILE_VAR}, optional>
+}}
+
+Any of {} can also be set with {}.", oauth::DEFAULT_REDIRECT_PORT, parser::DEFAULT_HEADER_TAGS, parser::DEFAULT_HEADER_PATTERNS, parser::DEFAULT_LIST_TYPES,
+        naming::DEFAULT_NAMING_PATTERN, naming::NAMING_VARIABLES.map(|x| format!("{{{x}}}")).join(", "),
+        CanvasClient::DEFAULT_TIMEOUT.as_secs_f64(), retry.max_retries, retry.base_delay.as_secs_f64(), retry.max_delay.as_secs_f64(),
+        requester::DEFAULT_ASSIGNMENT_GROUPS,
+        ENV_OVERRIDES.map(|x| x.1).join(", "), ENV_OVERRIDES.map(|x| format!("${}", x.0)).join(", "))
+    }
+
+    /// The question rules for a course, taking each field from the course's
+    /// config first, then the global config, then the defaults.
+    pub fn get_question_rules(&self, course_id: i64) -> Result<QuestionRules> {
+        let course: Option<&QuestionRulesConfig> = self.courses.get(&course_id.to_string()).map(|x| &x.question_rules);
+        self.build_question_rules(course)
+    }
+
+    fn build_question_rules(&self, course: Option<&QuestionRulesConfig>) -> Result<QuestionRules> {
+        let global: &QuestionRulesConfig = &self.question_rules;
+        let header_tags: Vec<String> = course.and_then(|x| x.header_tags.clone())
+            .or_else(|| global.header_tags.clone())
+            .unwrap_or_else(|| to_strings(&parser::DEFAULT_HEADER_TAGS));
+        let header_patterns: Vec<String> = course.and_then(|x| x.header_patterns.clone())
+            .or_else(|| global.header_patterns.clone())
+            .unwrap_or_else(|| to_strings(&parser::DEFAULT_HEADER_PATTERNS));
+        let list_types: Vec<String> = course.and_then(|x| x.list_types.clone())
+            .or_else(|| global.list_types.clone())
+            .unwrap_or_else(|| to_strings(&parser::DEFAULT_LIST_TYPES));
+        let bold_paragraphs: bool = course.and_then(|x| x.bold_paragraphs)
+            .or(global.bold_paragraphs)
+            .unwrap_or(true);
+
+        QuestionRules::new(&header_tags, &header_patterns, &list_types, bold_paragraphs)
+    }
+
+    /// The template file for a course, if the course or the global config sets one.
+    pub fn get_template_path(&self, course_id: i64) -> Option<&Path> {
+        self.courses.get(&course_id.to_string())
+            .and_then(|x| x.template.as_deref())
+            .or(self.template.as_deref())
+    }
+
+    /// The patterns for a course's lab groups, from the course's config, then the global config, then the defaults.
+    pub fn get_group_patterns(&self, course_id: i64) -> Result<Vec<Regex>> {
+        let course: Option<&CourseConfig> = self.courses.get(&course_id.to_string());
+        let patterns: Vec<String> = course.and_then(|x| x.assignment_groups.clone())
+            .or_else(|| self.assignment_groups.clone())
+            .unwrap_or_else(|| to_strings(&requester::DEFAULT_ASSIGNMENT_GROUPS));
+        build_group_patterns(&patterns)
+    }
+
+    /// Whether to pick from all of a course's assignments, ignoring groups.
+    pub fn is_all_groups(&self, course_id: i64) -> bool {
+        self.courses.get(&course_id.to_string())
+            .and_then(|x| x.all_groups)
+            .unwrap_or(self.all_groups)
+    }
+
+    /// Drops the hidden courses and the ones outside `term`, and puts the pinned ones first.
+    pub fn filter_courses(&self, courses: Vec<Course>) -> Vec<Course> {
+        let term: Option<String> = self.term.as_ref().map(|x| x.trim().to_lowercase()).filter(|x| !x.is_empty());
+        let (mut pinned, others): (Vec<Course>, Vec<Course>) = courses.into_iter()
+            .filter(|x| !self.courses.get(&x.id.to_string()).is_some_and(|x| x.hidden))
+            .partition(|x| self.courses.get(&x.id.to_string()).is_some_and(|x| x.pinned));
+
+        pinned.extend(others.into_iter().filter(|course| match &term {
+            Some(term) => course.term.as_ref().is_some_and(|x| x.name.to_lowercase().contains(term)),
+            None => true,
+        }));
+        pinned
+    }
+
+    /// The name the token is stored under, `credential` or the Canvas host.
+    pub fn get_credential_name(&self) -> String {
+        match &self.credential {
+            Some(name) => name.clone(),
+            None => Url::parse(&self.canvas_url).ok()
+                .and_then(|x| x.host_str().map(String::from))
+                .unwrap_or_else(|| self.canvas_url.clone()),
+        }
+    }
+
+    /// How long a request can take, from `requests`.
+    pub fn get_timeout(&self) -> Result<Duration> {
+        get_seconds("timeout", self.requests.timeout, CanvasClient::DEFAULT_TIMEOUT)
+    }
+
+    /// How failed requests are retried, from `requests`.
+    pub fn get_retry_policy(&self) -> Result<RetryPolicy> {
+        let default: RetryPolicy = RetryPolicy::default();
+        Ok(RetryPolicy {
+            max_retries: self.requests.max_retries.unwrap_or(default.max_retries),
+            base_delay: get_seconds("retry_delay", self.requests.retry_delay, default.base_delay)?,
+            max_delay: get_seconds("max_retry_delay", self.requests.max_retry_delay, default.max_delay)?,
+        })
+    }
+
+    /// A client for `canvas_url` with `token`, using the timeout and retries from `requests` and the response cache.
+    pub fn new_client(&self, token: &str) -> Result<CanvasClient> {
+        let client: CanvasClient = CanvasClient::new(&self.canvas_url, token)?
+            .timeout(self.get_timeout()?)
+            .retry_policy(self.get_retry_policy()?);
+        // without a home directory there's no cache, everything is just fetched.
+        match ResponseCache::open_default() {
+            Ok(cache) => Ok(client.with_cache(cache.for_credential(&self.get_credential_name()))),
+            Err(_) => Ok(client),
+        }
+    }
+
+    /// A client that only reads the response cache. It never sends a request, so it doesn't need a token.
+    pub fn get_offline_client(&self) -> Result<CanvasClient> {
+        Ok(self.new_client("")?.offline(true))
+    }
+
+    /// A client with the token from the config (or `$MAKER_TOKEN`) if it's
+    /// there, otherwise the stored one. Stored OAuth tokens are refreshed when
+    /// they expire. `get_passphrase` is only called if the token is in the encrypted file.
+    pub async fn get_client(&self, get_passphrase: fn() -> Result<String>) -> Result<CanvasClient> {
+        if let Some(token) = &self.token {
+            return self.new_client(token);
+        }
+        let name: String = self.get_credential_name();
+        let (stored, store) = credentials::load_token(&name, get_passphrase).await?.ok_or_else(|| {
+            Error::Config(format!("No token for `{name}`, run `maker auth login` or set `token` in `{}`", self.config_file.display()))
+        })?;
+
+        let Some(tokens) = OAuthTokens::from_stored(&stored) else {
+            return self.new_client(&stored);
+        };
+        let config: OAuthConfig = self.oauth.clone()
+            .ok_or_else(|| Error::Config(format!("`{name}` is an OAuth login, but `oauth` isn't set in `{}`", self.config_file.display())))?;
+        let file_only: bool = matches!(store, CredentialStore::EncryptedFile(_));
+        let client: CanvasClient = self.new_client(&tokens.access_token)?;
+        let session: OAuthSession = OAuthSession::new(&self.canvas_url, config, tokens, &name, file_only, get_passphrase)
+            .timeout(self.get_timeout()?);
+        Ok(client.with_oauth(session))
+    }
+
+    /// The pattern lab note paths are built from.
+    pub fn get_naming_pattern(&self) -> Result<NamingPattern> {
+        match &self.naming_pattern {
+            Some(pattern) => NamingPattern::new(pattern),
+            None => Ok(NamingPattern::default()),
+        }
+    }
+
+    /// The editor command split into the program and its arguments, from the
+    /// config's `editor`, then `$VISUAL`, then `$EDITOR`, then [`FALLBACK_EDITOR`].
+    pub fn get_editor_command(&self) -> Result<Vec<String>> {
+        let is_set = |x: &String| !x.trim().is_empty();
+        let editor: String = self.editor.clone().filter(is_set)
+            .or_else(|| env::var("VISUAL").ok().filter(is_set))
+            .or_else(|| env::var("EDITOR").ok().filter(is_set))
+            .unwrap_or_else(|| String::from(FALLBACK_EDITOR));
+
+        let command: Vec<String> = shell_words::split(&editor)
+            .map_err(|error| Error::Config(format!("Invalid editor command `{editor}`: {error}")))?;
+        if command.is_empty() {
+            return Err(Error::Config(format!("Invalid editor command `{editor}`")));
+        }
+        Ok(command)
+    }
+
+    // catches bad rules when the config is read, not when a course happens to use them.
+    fn check_rules(&self) -> Result<()> {
+        self.build_question_rules(None)?;
+        for (course_id, course) in self.courses.iter() {
+            if course_id.parse::<i64>().is_err() {
+                return Err(Error::Config(format!("Invalid course id `{course_id}` in `courses`, it must be a number")));
+            }
+            self.build_question_rules(Some(&course.question_rules))?;
+            if let Some(patterns) = &course.assignment_groups {
+                build_group_patterns(patterns)?;
+            }
+        }
+        if let Some(patterns) = &self.assignment_groups {
+            build_group_patterns(patterns)?;
+        }
+        Ok(())
+    }
+
+    // expands every path in the config, see `expand_path`.
+    fn expand_paths(&mut self, local_file: &Path) -> Result<()> {
+        let config_dir: PathBuf = match local_file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => env::current_dir().map_err(|error| Error::io(".", error))?,
+        };
+
+        self.base_dir = expand_path(&self.base_dir, &config_dir)?;
+        if let Some(template) = &self.template {
+            self.template = Some(expand_path(template, &config_dir)?);
+        }
+        for course in self.courses.values_mut() {
+            if let Some(template) = &course.template {
+                course.template = Some(expand_path(template, &config_dir)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds, reads and validates the config.
+    ///
+    /// The config file is `config_file` if given, then `$MAKER_CONFIG`, then
+    /// `./local.json`, then `$XDG_CONFIG_HOME/maker/config.json`. A profile from
+    /// its `profiles` (`profile`, then `$MAKER_PROFILE`, then `default_profile`)
+    /// is laid over the top-level settings, and `MAKER_*` environment variables
+    /// override both.
+    pub fn get_local_data(config_file: Option<&Path>, profile: Option<&str>) -> Result<LocalData> {
+
+        let local_file: PathBuf = find_config_file(config_file)?;
+        let local_file_name: String = local_file.display().to_string();
+
+        // open the JSON file and read it into a string.
+        let local_data: String = fs::read_to_string(&local_file).map_err(|error| {
+            if error.kind() == ErrorKind::NotFound {
+                Error::Config(format!("File `{local_file_name}` not found!: {error}\n\n{}", LocalData::get_config_help(&local_file_name)))
+            } else {
+                Error::io(&local_file, error)
+            }
+        })?;
+
+        // make sure the JSON is the correct format.
+        let invalid_json = |error: serde_json::Error| {
+            Error::Config(format!("Invalid JSON format in `{local_file_name}`: {error}.\n\n{}", LocalData::get_config_help(&local_file_name)))
+        };
+        let mut json: Value = serde_json::from_str(&local_data).map_err(invalid_json)?;
+        let profile: Option<String> = profile.map(String::from).or_else(|| env::var(PROFILE_VAR).ok());
+        apply_profile(&mut json, profile.as_deref())?;
+        apply_env_overrides(&mut json);
+
+        let mut local_data: LocalData = serde_json::from_value(json).map_err(invalid_json)?;
+        local_data.canvas_url = requester::parse_canvas_url(&local_data.canvas_url)?;
+        local_data.expand_paths(&local_file)?;
+        local_data.check_rules()?;
+        local_data.get_timeout()?;
+        local_data.get_retry_policy()?;
+        local_data.get_naming_pattern()?;
+        local_data.config_file = local_file;
+
+        Ok(local_data)
+    }
+}
+
+// the first config file that exists, in the order `get_local_data` describes.
+fn find_config_file(config_file: Option<&Path>) -> Result<PathBuf> {
+    // asked for by name, so it has to be there.
+    if let Some(config_file) = config_file {
+        return Ok(config_file.to_path_buf());
+    }
+    if let Some(config_file) = env::var_os(CONFIG_VAR).filter(|x| !x.is_empty()) {
+        return Ok(PathBuf::from(config_file));
+    }
+
+    let mut searched: Vec<PathBuf> = vec![PathBuf::from(LOCAL_CONFIG_FILE)];
+    if let Some(config_dir) = get_config_dir() {
+        searched.push(config_dir.join("maker").join("config.json"));
+    }
+    if let Some(found) = searched.iter().find(|x| x.is_file()) {
+        return Ok(found.clone());
+    }
+
+    let searched: Vec<String> = searched.iter().map(|x| format!("  {}", x.display())).collect();
+    Err(Error::Config(format!(
+        "No config file found! Pass one with `--config` or `${CONFIG_VAR}`, or create one of:\n{}\n\n{}",
+        searched.join("\n"), LocalData::get_config_help(LOCAL_CONFIG_FILE),
+    )))
+}
+
+// `$XDG_CONFIG_HOME`, which defaults to `~/.config`.
+pub(crate) fn get_config_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME").filter(|x| !x.is_empty()) {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => get_home_dir().ok().map(|x| Path::new(&x).join(".config")),
+    }
+}
+
+// lays the chosen profile over the top-level settings.
+fn apply_profile(json: &mut Value, profile: Option<&str>) -> Result<()> {
+    let Value::Object(map) = json else {
+        // not an object, let deserializing explain what's wrong.
+        return Ok(());
+    };
+    let profiles: Option<Value> = map.remove("profiles");
+    let default_profile: Option<String> = map.remove("default_profile").and_then(|x| x.as_str().map(String::from));
+    let Some(name) = profile.map(String::from).or(default_profile) else {
+        return Ok(());
+    };
+
+    let profile: Value = match profiles {
+        Some(Value::Object(mut profiles)) => match profiles.remove(&name) {
+            Some(profile) => profile,
+            None => {
+                let names: Vec<String> = profiles.keys().cloned().collect();
+                return Err(Error::Config(format!("No profile named `{name}`, the profiles are: {}", names.join(", "))));
+            },
+        },
+        _ => return Err(Error::Config(format!("No profile named `{name}`, the config doesn't have `profiles`"))),
+    };
+    merge_json(json, profile);
+    Ok(())
+}
+
+// objects are merged key by key, anything else replaces what was there.
+fn merge_json(base: &mut Value, over: Value) {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, value) in over {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    },
+                }
+            }
+        },
+        (base, over) => *base = over,
+    }
+}
+
+fn apply_env_overrides(json: &mut Value) {
+    let Value::Object(map) = json else {
+        return;
+    };
+    for (var, key) in ENV_OVERRIDES.iter() {
+        if let Ok(value) = env::var(var) {
+            map.insert(key.to_string(), Value::String(value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn home_and_variables_are_expanded() {
+        let home: String = get_home_dir().unwrap();
+        env::set_var("MAKER_TEST_NOTES", "notes");
+        let config_dir: &Path = Path::new("/etc/maker");
+
+        assert_eq!(expand_path(Path::new("~"), config_dir).unwrap(), PathBuf::from(&home));
+        assert_eq!(expand_path(Path::new("~/labs"), config_dir).unwrap(), Path::new(&home).join("labs"));
+        assert_eq!(expand_path(Path::new("/srv/$MAKER_TEST_NOTES/a"), config_dir).unwrap(), PathBuf::from("/srv/notes/a"));
+        assert_eq!(expand_path(Path::new("/srv/${MAKER_TEST_NOTES}_old"), config_dir).unwrap(), PathBuf::from("/srv/notes_old"));
+        assert_eq!(expand_variables("a $ b $").unwrap(), "a $ b $");
+    }
+
+    #[test]
+    fn relative_paths_are_from_the_config_dir() {
+        let config_dir: &Path = Path::new("/etc/maker");
+        assert_eq!(expand_path(Path::new("labs"), config_dir).unwrap(), PathBuf::from("/etc/maker/labs"));
+        // `~` only means home at the start of a segment.
+        assert_eq!(expand_path(Path::new("~labs"), config_dir).unwrap(), PathBuf::from("/etc/maker/~labs"));
+    }
+
+    #[test]
+    fn bad_variables_are_errors() {
+        assert!(matches!(expand_variables("/srv/${MAKER_TEST_NOTES"), Err(Error::Config(_))));
+        assert!(matches!(expand_variables("/srv/$MAKER_TEST_NEVER_SET"), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn profile_is_merged_over_the_settings() {
+        let mut config: Value = json!({
+            "name": "Ada",
+            "base_dir": "~/labs",
+            "question_rules": {"header_tags": ["h2"], "list_types": ["ol"]},
+            "profiles": {"work": {"base_dir": "~/work", "question_rules": {"list_types": ["ul"]}}},
+        });
+        apply_profile(&mut config, Some("work")).unwrap();
+        assert_eq!(config, json!({
+            "name": "Ada",
+            "base_dir": "~/work",
+            "question_rules": {"header_tags": ["h2"], "list_types": ["ul"]},
+        }));
+    }
+
+    #[test]
+    fn default_profile_is_used_unless_one_is_chosen() {
+        let config: Value = json!({
+            "base_dir": "~/labs",
+            "default_profile": "home",
+            "profiles": {"home": {"base_dir": "~/home"}, "work": {"base_dir": "~/work"}},
+        });
+
+        let mut defaulted: Value = config.clone();
+        apply_profile(&mut defaulted, None).unwrap();
+        assert_eq!(defaulted, json!({"base_dir": "~/home"}));
+
+        let mut chosen: Value = config.clone();
+        apply_profile(&mut chosen, Some("work")).unwrap();
+        assert_eq!(chosen, json!({"base_dir": "~/work"}));
+    }
+
+    #[test]
+    fn unknown_profile_is_an_error() {
+        let mut config: Value = json!({"profiles": {"home": {}}});
+        assert!(matches!(apply_profile(&mut config, Some("work")), Err(Error::Config(_))));
+        let mut config: Value = json!({"base_dir": "~/labs"});
+        assert!(matches!(apply_profile(&mut config, Some("work")), Err(Error::Config(_))));
+
+        let mut config: Value = json!({"base_dir": "~/labs"});
+        apply_profile(&mut config, None).unwrap();
+        assert_eq!(config, json!({"base_dir": "~/labs"}));
+    }
+
+    #[test]
+    fn env_overrides_replace_settings() {
+        env::set_var("MAKER_TERM", "Spring 2027");
+        let mut config: Value = json!({"term": "Fall 2026", "name": "Ada"});
+        apply_env_overrides(&mut config);
+        env::remove_var("MAKER_TERM");
+        assert_eq!(config["term"], json!("Spring 2027"));
+        assert_eq!(config["name"], json!("Ada"));
+    }
+}
@@ -0,0 +1,564 @@
+use crate::error::Result;
+use crate::parser::{BaseHtml, ChildHtml, HtmlNode};
+
+const INDENT: &str = "    ";
+
+// a converted block, lists can sit right under the line before them.
+struct Block {
+    text: String,
+    is_list: bool,
+}
+
+// how an inline piece is spaced from its neighbours, since the html parser drops whitespace-only text.
+#[derive(PartialEq, Clone, Copy)]
+enum Spacing {
+    Text,
+    Element,
+    // sub, sup and line breaks never get a space added around them.
+    Sticky,
+}
+
+/// Converts an HTML document into markdown.
+///
+/// Headings are pushed down by `heading_offset` levels (capped at `######`),
+/// so a description can be nested under a lab note's own headings.
+pub fn convert_html(doc: &str, heading_offset: usize) -> Result<String> {
+    let base_dom: BaseHtml = BaseHtml::convert_doc_to_struct(doc)?;
+    Ok(convert_nodes(&base_dom.children, heading_offset))
+}
+
+pub(crate) fn convert_nodes(nodes: &[HtmlNode], heading_offset: usize) -> String {
+    join_blocks(convert_blocks(nodes, heading_offset), false)
+}
+
+// blocks are separated by a blank line, except lists nested in a list item.
+fn join_blocks(blocks: Vec<Block>, tight_lists: bool) -> String {
+    let mut markdown_str: String = String::new();
+    for (i, block) in blocks.into_iter().enumerate() {
+        if i > 0 {
+            markdown_str.push_str(if tight_lists && block.is_list { "\n" } else { "\n\n" });
+        }
+        markdown_str.push_str(&block.text);
+    }
+    markdown_str
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(name,
+        "p" | "div" | "section" | "article" | "header" | "footer" | "main" | "aside" | "nav"
+        | "figure" | "figcaption" | "address" | "center" | "details" | "summary" | "dl" | "dt" | "dd"
+        | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+        | "pre" | "blockquote" | "hr" | "ul" | "ol" | "table"
+    )
+}
+
+fn convert_blocks(nodes: &[HtmlNode], heading_offset: usize) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut inline_nodes: Vec<&HtmlNode> = Vec::new();
+
+    for node in nodes {
+        match node {
+            HtmlNode::Element(element) if is_block(&element.name) => {
+                // text before a block is its own paragraph.
+                push_paragraph(&mut blocks, &inline_nodes);
+                inline_nodes.clear();
+
+                let text: String = convert_block(element, heading_offset);
+                if !text.trim().is_empty() {
+                    let is_list: bool = element.name == "ul" || element.name == "ol";
+                    blocks.push(Block { text, is_list });
+                }
+            },
+            _ => inline_nodes.push(node),
+        }
+    }
+    push_paragraph(&mut blocks, &inline_nodes);
+
+    blocks
+}
+
+fn push_paragraph(blocks: &mut Vec<Block>, inline_nodes: &[&HtmlNode]) {
+    let text: String = clean_inline(&convert_inline(inline_nodes.iter().copied()));
+    if !text.is_empty() {
+        blocks.push(Block { text, is_list: false });
+    }
+}
+
+fn convert_block(element: &ChildHtml, heading_offset: usize) -> String {
+    match element.name.as_str() {
+        "p" => clean_inline(&convert_inline(&element.children)),
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level: usize = element.name[1..].parse::<usize>().unwrap_or(1) + heading_offset;
+            let text: String = clean_inline(&convert_inline(&element.children)).replace("\\\n", " ");
+            if text.is_empty() {
+                return text;
+            }
+            format!("{} {text}", "#".repeat(level.min(6)))
+        },
+        "dt" | "summary" => {
+            let text: String = clean_inline(&convert_inline(&element.children));
+            wrap_inline(&text, "**")
+        },
+        "pre" => convert_pre(element),
+        "blockquote" => {
+            let inner: String = convert_nodes(&element.children, heading_offset);
+            inner.lines()
+                .map(|x| if x.is_empty() { String::from(">") } else { format!("> {x}") })
+                .collect::<Vec<String>>()
+                .join("\n")
+        },
+        "hr" => String::from("---"),
+        "ul" | "ol" => convert_list(element, heading_offset),
+        "table" => convert_table(element),
+        // everything else just holds more blocks.
+        _ => convert_nodes(&element.children, heading_offset),
+    }
+}
+
+fn convert_list(list: &ChildHtml, heading_offset: usize) -> String {
+    let ordered: bool = list.name == "ol";
+    let mut num: i64 = list.get_attribute("start")
+        .and_then(|x| x.trim().parse::<i64>().ok())
+        .unwrap_or(1);
+
+    let mut lines: Vec<String> = Vec::new();
+    for child in list.children.iter() {
+        let HtmlNode::Element(item) = child else {
+            continue;
+        };
+        if item.name == "li" {
+            let marker: String = if ordered { format!("{num}.") } else { String::from("-") };
+            num += 1;
+            // the item's own lines are indented under its marker.
+            let content: String = join_blocks(convert_blocks(&item.children, heading_offset), true);
+            lines.push(indent_lines(&content, &format!("{marker} "), INDENT));
+        } else if item.name == "ul" || item.name == "ol" {
+            // a list right inside a list belongs to the item before it.
+            let content: String = convert_list(item, heading_offset);
+            lines.push(indent_lines(&content, INDENT, INDENT));
+        }
+    }
+    lines.join("\n")
+}
+
+// prefixes the first line with `first` and every other non-empty line with `rest`.
+fn indent_lines(text: &str, first: &str, rest: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if i == 0 {
+            lines.push(format!("{first}{line}").trim_end().to_string());
+        } else if line.is_empty() {
+            lines.push(String::new());
+        } else {
+            lines.push(format!("{rest}{line}"));
+        }
+    }
+    if lines.is_empty() {
+        lines.push(first.trim_end().to_string());
+    }
+    lines.join("\n")
+}
+
+fn convert_pre(pre: &ChildHtml) -> String {
+    let mut language: Option<&str> = get_language(pre);
+    for child in pre.children.iter() {
+        if let HtmlNode::Element(code) = child {
+            if code.name == "code" {
+                language = language.or(get_language(code));
+            }
+        }
+    }
+
+    let raw: String = get_raw_text(&pre.children);
+    let code: &str = raw.strip_prefix('\n').unwrap_or(&raw).trim_end();
+
+    // the fence has to be longer than any run of backticks in the code.
+    let longest_run: usize = code.split(|x| x != '`').map(|x| x.len()).max().unwrap_or(0);
+    let fence: String = "`".repeat((longest_run + 1).max(3));
+    format!("{fence}{}\n{code}\n{fence}", language.unwrap_or(""))
+}
+
+fn get_language(element: &ChildHtml) -> Option<&str> {
+    element.classes.iter().find_map(|x| {
+        x.strip_prefix("language-").or_else(|| x.strip_prefix("lang-"))
+    })
+}
+
+fn get_raw_text(nodes: &[HtmlNode]) -> String {
+    let mut text: String = String::new();
+    for node in nodes {
+        match node {
+            HtmlNode::Text(value) => text.push_str(&decode_entities(value)),
+            HtmlNode::Element(element) if element.name == "br" => text.push('\n'),
+            HtmlNode::Element(element) => text.push_str(&get_raw_text(&element.children)),
+        }
+    }
+    text
+}
+
+/// The text of some nodes on one line, with the whitespace collapsed, for matching against.
+pub(crate) fn get_text(nodes: &[HtmlNode]) -> String {
+    collapse_whitespace(&get_raw_text(nodes)).trim().to_string()
+}
+
+fn convert_table(table: &ChildHtml) -> String {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    get_table_rows(&table.children, &mut rows);
+
+    let width: usize = rows.iter().map(|x| x.len()).max().unwrap_or(0);
+    if width == 0 {
+        return String::new();
+    }
+
+    // markdown tables always need a header, so the first row is used as one.
+    let mut lines: Vec<String> = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        let mut cells: Vec<&str> = row.iter().map(|x| x.as_str()).collect();
+        cells.resize(width, "");
+        lines.push(format!("| {} |", cells.join(" | ")));
+        if i == 0 {
+            lines.push(format!("|{}", " --- |".repeat(width)));
+        }
+    }
+    lines.join("\n")
+}
+
+fn get_table_rows(nodes: &[HtmlNode], rows: &mut Vec<Vec<String>>) {
+    for node in nodes {
+        let HtmlNode::Element(element) = node else {
+            continue;
+        };
+        match element.name.as_str() {
+            "thead" | "tbody" | "tfoot" => get_table_rows(&element.children, rows),
+            "tr" => {
+                let cells: Vec<String> = element.children.iter()
+                    .filter_map(|x| match x {
+                        HtmlNode::Element(cell) if cell.name == "td" || cell.name == "th" => Some(cell),
+                        _ => None,
+                    })
+                    .map(|cell| {
+                        clean_inline(&convert_inline(&cell.children))
+                            .replace("\\\n", "<br>")
+                            .replace('\n', " ")
+                            .replace('|', "\\|")
+                    })
+                    .collect();
+                rows.push(cells);
+            },
+            _ => {},
+        }
+    }
+}
+
+fn convert_inline<'a>(nodes: impl IntoIterator<Item = &'a HtmlNode>) -> String {
+    let mut markdown_str: String = String::new();
+    let mut last_spacing: Spacing = Spacing::Text;
+
+    for node in nodes {
+        let (piece, spacing) = match node {
+            HtmlNode::Text(text) => (collapse_whitespace(&decode_entities(text)), Spacing::Text),
+            HtmlNode::Element(element) => {
+                let spacing: Spacing = match element.name.as_str() {
+                    "sub" | "sup" | "br" => Spacing::Sticky,
+                    _ => Spacing::Element,
+                };
+                (convert_inline_element(element), spacing)
+            },
+        };
+        if piece.is_empty() {
+            continue;
+        }
+
+        let next_to_element: bool = (spacing == Spacing::Element && last_spacing != Spacing::Sticky)
+            || (last_spacing == Spacing::Element && spacing != Spacing::Sticky);
+        if next_to_element && needs_space(&markdown_str, &piece) {
+            markdown_str.push(' ');
+        }
+        markdown_str.push_str(&piece);
+        last_spacing = spacing;
+    }
+    markdown_str
+}
+
+// the html parser drops the whitespace between elements, so put it back between words.
+fn needs_space(before: &str, after: &str) -> bool {
+    match (before.chars().last(), after.chars().next()) {
+        (Some(last), Some(first)) => {
+            // images start with `!`, but they're words too.
+            let first_is_word: bool = !".,;:!?)]}\"'/-”’".contains(first) || after.starts_with("![");
+            !last.is_whitespace() && !first.is_whitespace()
+                && !"([{\"'/-“‘".contains(last)
+                && first_is_word
+        },
+        _ => false,
+    }
+}
+
+/// Converts one inline element (a link, bold text, an image...) into markdown.
+pub(crate) fn convert_inline_element(element: &ChildHtml) -> String {
+    match element.name.as_str() {
+        "br" => String::from("\\\n"),
+        "strong" | "b" => wrap_inline(&convert_inline(&element.children), "**"),
+        "em" | "i" => wrap_inline(&convert_inline(&element.children), "_"),
+        "del" | "s" | "strike" => wrap_inline(&convert_inline(&element.children), "~~"),
+        "code" | "kbd" | "samp" | "tt" => {
+            let code: String = collapse_whitespace(&get_raw_text(&element.children));
+            let code: &str = code.trim();
+            if code.is_empty() {
+                String::new()
+            } else if code.contains('`') {
+                format!("`` {code} ``")
+            } else {
+                format!("`{code}`")
+            }
+        },
+        "a" => {
+            let text: String = clean_inline(&convert_inline(&element.children));
+            match element.get_attribute("href") {
+                Some(href) if text.is_empty() => format!("<{href}>"),
+                Some(href) => format!("[{text}]({})", href.replace(' ', "%20")),
+                None => text,
+            }
+        },
+        "img" => {
+            let alt: &str = element.get_attribute("alt").unwrap_or("");
+            match element.get_attribute("src") {
+                Some(src) => format!("![{alt}]({})", src.replace(' ', "%20")),
+                None => String::new(),
+            }
+        },
+        // markdown has nothing for these, but html in markdown renders fine.
+        "sub" | "sup" => format!("<{0}>{1}</{0}>", element.name, convert_inline(&element.children).trim()),
+        "script" | "style" => String::new(),
+        _ => convert_inline(&element.children),
+    }
+}
+
+// wraps text in a marker, keeping the marker right up against the text.
+fn wrap_inline(text: &str, marker: &str) -> String {
+    let trimmed: &str = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+    let before: &str = if text.starts_with(char::is_whitespace) { " " } else { "" };
+    let after: &str = if text.ends_with(char::is_whitespace) { " " } else { "" };
+    format!("{before}{marker}{trimmed}{marker}{after}")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed: String = String::with_capacity(text.len());
+    let mut last_was_space: bool = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_was_space {
+                collapsed.push(' ');
+            }
+            last_was_space = true;
+        } else {
+            collapsed.push(c);
+            last_was_space = false;
+        }
+    }
+    collapsed
+}
+
+// squashes the spaces left where pieces were joined, and trims every line.
+fn clean_inline(text: &str) -> String {
+    text.split('\n')
+        .map(|x| collapse_whitespace(x).trim().to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Decodes the HTML entities the parser leaves in text, like `&amp;` and `&#39;`.
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut decoded: String = String::with_capacity(text.len());
+    let mut rest: &str = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        // entities are short, anything longer is just an ampersand.
+        let entity: Option<(char, usize)> = rest[1..].find(';')
+            .filter(|x| *x <= 10)
+            .and_then(|end| get_entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            },
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn get_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code: Option<u32> = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => number.parse::<u32>().ok(),
+        };
+        return code.and_then(char::from_u32);
+    }
+    let c: char = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bull" => '•',
+        "middot" => '·',
+        "times" => '×',
+        "divide" => '÷',
+        "plusmn" => '±',
+        "deg" => '°',
+        "le" => '≤',
+        "ge" => '≥',
+        "ne" => '≠',
+        "larr" => '←',
+        "rarr" => '→',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "sect" => '§',
+        "para" => '¶',
+        _ => return None,
+    };
+    Some(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(doc: &str) -> String {
+        convert_html(doc, 0).unwrap()
+    }
+
+    #[test]
+    fn headings() {
+        assert_eq!(convert("<h1>One</h1><h3>Three</h3><h6>Six</h6>"), "# One\n\n### Three\n\n###### Six");
+        // pushed down, but never past `######`.
+        assert_eq!(convert_html("<h2>Two</h2><h6>Six</h6>", 2).unwrap(), "#### Two\n\n###### Six");
+    }
+
+    #[test]
+    fn line_break_in_a_heading_is_a_space() {
+        assert_eq!(convert("<h2>Part<br>One</h2>"), "## Part One");
+    }
+
+    #[test]
+    fn paragraphs() {
+        assert_eq!(convert("<p>First  paragraph.</p><p>Second\nparagraph.</p>"), "First paragraph.\n\nSecond paragraph.");
+    }
+
+    #[test]
+    fn bold_and_italics() {
+        assert_eq!(convert("<p>Some <strong>bold</strong> and <em>italic</em> text.</p>"), "Some **bold** and _italic_ text.");
+        assert_eq!(convert("<p><b>Bold</b><i>italic</i></p>"), "**Bold** _italic_");
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(convert(r#"<p>Read <a href="https://x.org/a">the docs</a>.</p>"#), "Read [the docs](https://x.org/a).");
+        assert_eq!(convert(r#"<p><a href="https://x.org/my file.pdf">file</a></p>"#), "[file](https://x.org/my%20file.pdf)");
+        assert_eq!(convert(r#"<p><a href="https://x.org"></a></p>"#), "<https://x.org>");
+    }
+
+    #[test]
+    fn images() {
+        assert_eq!(convert(r#"<p><img src="https://x.org/a b.png" alt="A diagram"></p>"#), "![A diagram](https://x.org/a%20b.png)");
+    }
+
+    #[test]
+    fn code_blocks() {
+        assert_eq!(
+            convert(r#"<pre><code class="language-rust">fn main() {
+    println!("&lt;hi&gt;");
+}</code></pre>"#),
+            "```rust\nfn main() {\n    println!(\"<hi>\");\n}\n```",
+        );
+    }
+
+    #[test]
+    fn code_block_fence_is_longer_than_its_backticks() {
+        assert_eq!(convert("<pre>a ```b``` c</pre>"), "````\na ```b``` c\n````");
+    }
+
+    #[test]
+    fn inline_code() {
+        assert_eq!(convert("<p>Run <code>cargo  test</code> now.</p>"), "Run `cargo test` now.");
+        assert_eq!(convert("<p><code>a`b</code></p>"), "`` a`b ``");
+    }
+
+    #[test]
+    fn blockquotes() {
+        assert_eq!(convert("<blockquote><p>One</p><p>Two</p></blockquote>"), "> One\n>\n> Two");
+    }
+
+    #[test]
+    fn horizontal_rules() {
+        assert_eq!(convert("<p>Above</p><hr><p>Below</p>"), "Above\n\n---\n\nBelow");
+    }
+
+    #[test]
+    fn line_breaks() {
+        assert_eq!(convert("<p>One<br>Two</p>"), "One\\\nTwo");
+    }
+
+    #[test]
+    fn tables() {
+        assert_eq!(
+            convert("<table><thead><tr><th>Name</th><th>Size</th></tr></thead><tbody><tr><td>a</td><td>1</td></tr><tr><td>b</td></tr></tbody></table>"),
+            "| Name | Size |\n| --- | --- |\n| a | 1 |\n| b |  |",
+        );
+    }
+
+    #[test]
+    fn table_cells_escape_pipes_and_keep_line_breaks() {
+        assert_eq!(
+            convert("<table><tr><th>Expression</th></tr><tr><td>a | b<br>c</td></tr></table>"),
+            "| Expression |\n| --- |\n| a \\| b<br>c |",
+        );
+    }
+
+    #[test]
+    fn sub_and_sup() {
+        assert_eq!(convert("<p>H<sub>2</sub>O and x<sup>2</sup></p>"), "H<sub>2</sub>O and x<sup>2</sup>");
+    }
+
+    #[test]
+    fn nested_lists() {
+        assert_eq!(
+            convert("<ul><li>One<ol><li>First</li><li>Second</li></ol></li><li>Two</li></ul>"),
+            "- One\n    1. First\n    2. Second\n- Two",
+        );
+    }
+
+    #[test]
+    fn ordered_list_start() {
+        assert_eq!(convert(r#"<ol start="4"><li>Four</li><li>Five</li></ol>"#), "4. Four\n5. Five");
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(decode_entities("a &amp; b &lt;c&gt; &#39;d&#x27; &rarr; &bogus; & e"), "a & b <c> 'd' → &bogus; & e");
+        assert_eq!(convert("<p>Tom&nbsp;&amp;&nbsp;Jerry</p>"), "Tom & Jerry");
+    }
+}
@@ -0,0 +1,172 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::error::{Error, Result};
+
+/// The keyring service tokens are stored under.
+pub const KEYRING_SERVICE: &str = "maker";
+/// The environment variable with the passphrase for the encrypted credentials file.
+pub const PASSPHRASE_VAR: &str = "MAKER_PASSPHRASE";
+
+// pbkdf2-sha256 rounds for turning the passphrase into a key.
+const KEY_ROUNDS: u32 = 600_000;
+const SALT_LENGTH: usize = 16;
+
+/// Where a token is stored.
+pub enum CredentialStore {
+    /// The OS secret store: Secret Service, macOS Keychain or Windows Credential Manager.
+    Keyring,
+    /// A passphrase-encrypted file.
+    EncryptedFile(PathBuf),
+}
+
+impl fmt::Display for CredentialStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CredentialStore::Keyring => write!(f, "the system keyring"),
+            CredentialStore::EncryptedFile(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+// one token in the credentials file, everything base64 encoded.
+#[derive(Serialize, Deserialize)]
+struct EncryptedToken {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// The encrypted credentials file, `$XDG_CONFIG_HOME/maker/credentials.json`.
+pub fn get_credentials_file() -> Result<PathBuf> {
+    config::get_config_dir()
+        .map(|x| x.join("maker").join("credentials.json"))
+        .ok_or_else(|| Error::Credential(String::from("Can't find the config directory, `$HOME` isn't set")))
+}
+
+// the keyring talks to the OS synchronously, so keep it off the async threads.
+// Any failure (no secret service running, locked keychain...) is just `None`.
+async fn with_keyring<T: Send + 'static>(name: &str, action: impl FnOnce(keyring::Entry) -> keyring::Result<T> + Send + 'static) -> Option<T> {
+    let name: String = name.to_string();
+    tokio::task::spawn_blocking(move || keyring::Entry::new(KEYRING_SERVICE, &name).and_then(action).ok())
+        .await
+        .ok()
+        .flatten()
+}
+
+fn read_credentials_file(path: &Path) -> Result<HashMap<String, EncryptedToken>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents).map_err(|error| Error::json("the credentials file", error)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(error) => Err(Error::io(path, error)),
+    }
+}
+
+fn write_credentials_file(path: &Path, tokens: &HashMap<String, EncryptedToken>) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|error| Error::io(dir, error))?;
+    }
+    let contents: String = serde_json::to_string_pretty(tokens).map_err(|error| Error::json("the credentials file", error))?;
+
+    let mut options: fs::OpenOptions = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // only the user should be able to read it, even encrypted. The mode is set when it's
+    // created, so it's never readable by others, and tightened if it already existed.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file: fs::File = options.open(path).map_err(|error| Error::io(path, error))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600)).map_err(|error| Error::io(path, error))?;
+    }
+    file.write_all(contents.as_bytes()).map_err(|error| Error::io(path, error))
+}
+
+fn get_key(passphrase: &str, salt: &[u8]) -> Key<Aes256Gcm> {
+    let mut key: Key<Aes256Gcm> = Key::<Aes256Gcm>::default();
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, KEY_ROUNDS, &mut key);
+    key
+}
+
+fn encrypt(token: &str, passphrase: &str) -> Result<EncryptedToken> {
+    let mut salt: [u8; SALT_LENGTH] = [0; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    let nonce: Nonce<_> = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let cipher: Aes256Gcm = Aes256Gcm::new(&get_key(passphrase, &salt));
+    let ciphertext: Vec<u8> = cipher.encrypt(&nonce, token.as_bytes())
+        .map_err(|_| Error::Credential(String::from("Couldn't encrypt the token")))?;
+
+    Ok(EncryptedToken {
+        salt: BASE64.encode(salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn decrypt(encrypted: &EncryptedToken, passphrase: &str) -> Result<String> {
+    let corrupted = || Error::Credential(String::from("The credentials file is corrupted"));
+    let salt: Vec<u8> = BASE64.decode(&encrypted.salt).map_err(|_| corrupted())?;
+    let nonce: Vec<u8> = BASE64.decode(&encrypted.nonce).map_err(|_| corrupted())?;
+    let ciphertext: Vec<u8> = BASE64.decode(&encrypted.ciphertext).map_err(|_| corrupted())?;
+    if nonce.len() != 12 {
+        return Err(corrupted());
+    }
+
+    let cipher: Aes256Gcm = Aes256Gcm::new(&get_key(passphrase, &salt));
+    let token: Vec<u8> = cipher.decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| Error::Credential(String::from("Wrong passphrase for the credentials file")))?;
+    String::from_utf8(token).map_err(|_| corrupted())
+}
+
+/// Stores a token under `name`, in the keyring if there is one, otherwise in
+/// the encrypted credentials file with the passphrase from `get_passphrase`.
+/// With `file_only`, the keyring isn't tried.
+pub async fn save_token(name: &str, token: &str, file_only: bool, get_passphrase: impl FnOnce() -> Result<String>) -> Result<CredentialStore> {
+    if !file_only {
+        let token: String = token.to_string();
+        if with_keyring(name, move |entry| entry.set_password(&token)).await.is_some() {
+            return Ok(CredentialStore::Keyring);
+        }
+    }
+
+    let path: PathBuf = get_credentials_file()?;
+    let mut tokens: HashMap<String, EncryptedToken> = read_credentials_file(&path)?;
+    tokens.insert(name.to_string(), encrypt(token, &get_passphrase()?)?);
+    write_credentials_file(&path, &tokens)?;
+    Ok(CredentialStore::EncryptedFile(path))
+}
+
+/// Finds the token stored under `name`, looking in the keyring first, then the
+/// encrypted credentials file. `get_passphrase` is only called if it's in the file.
+pub async fn load_token(name: &str, get_passphrase: impl FnOnce() -> Result<String>) -> Result<Option<(String, CredentialStore)>> {
+    // no keyring, or nothing in it, both mean looking in the file.
+    if let Some(token) = with_keyring(name, |entry| entry.get_password()).await {
+        return Ok(Some((token, CredentialStore::Keyring)));
+    }
+
+    let path: PathBuf = get_credentials_file()?;
+    let tokens: HashMap<String, EncryptedToken> = read_credentials_file(&path)?;
+    match tokens.get(name) {
+        Some(encrypted) => {
+            let token: String = decrypt(encrypted, &get_passphrase()?)?;
+            Ok(Some((token, CredentialStore::EncryptedFile(path))))
+        },
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,111 @@
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::{fmt, io};
+use std::path::PathBuf;
+
+/// A `Result` with this crate's [`Error`].
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Everything that can go wrong while building or submitting a lab note.
+#[derive(Debug)]
+pub enum Error {
+    /// The local config is missing or invalid.
+    Config(String),
+    /// The request never got a response (timeout, dns, tls...).
+    Request(reqwest::Error),
+    /// Canvas answered, but not with a success.
+    Status { status: StatusCode, url: String, body: String },
+    /// The JSON didn't have the shape we expected.
+    Json { context: String, source: serde_json::Error },
+    /// The assignment HTML couldn't be parsed.
+    Html(String),
+    /// The lab note template couldn't be rendered.
+    Template(String),
+    /// Reading or writing the filesystem failed.
+    Io { path: PathBuf, source: io::Error },
+    /// Canvas doesn't have what we were looking for.
+    NotFound(String),
+    /// The stored token couldn't be saved or read.
+    Credential(String),
+    /// Canvas was needed, but the client is offline.
+    Offline(String),
+    /// The user backed out of a prompt.
+    Cancelled,
+    /// The prompt itself failed.
+    Prompt(String),
+}
+
+impl Error {
+    /// A JSON error, with what we were trying to read.
+    pub fn json(context: &str, source: serde_json::Error) -> Error {
+        Error::Json { context: context.to_string(), source }
+    }
+
+    /// A filesystem error, with the path that caused it.
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Error {
+        Error::Io { path: path.into(), source }
+    }
+
+    // canvas sends `{"errors": [{"message": "..."}]}` or `{"message": "..."}`, fall back to the raw body.
+    fn get_canvas_message(body: &str) -> String {
+        let messages: Vec<String> = match serde_json::from_str::<Value>(body) {
+            Ok(Value::Object(map)) => match (map.get("errors"), map.get("message")) {
+                (Some(Value::Array(errors)), _) => errors.iter()
+                    .filter_map(|x| x.get("message").and_then(|m| m.as_str()))
+                    .map(String::from)
+                    .collect(),
+                (_, Some(Value::String(message))) => vec![message.to_string()],
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+        if messages.is_empty() {
+            body.trim().to_string()
+        } else {
+            messages.join("; ")
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Config(message) => write!(f, "Config error: {message}"),
+            Error::Request(error) => write!(f, "Request failed: {error}"),
+            Error::Status { status, url, body } => {
+                write!(f, "Canvas returned {status} for {url}")?;
+                let message: String = Error::get_canvas_message(body);
+                if !message.is_empty() {
+                    write!(f, ": {message}")?;
+                }
+                Ok(())
+            },
+            Error::Json { context, source } => write!(f, "Unexpected JSON in {context}: {source}"),
+            Error::Html(message) => write!(f, "Couldn't parse the HTML Document: {message}"),
+            Error::Template(message) => write!(f, "Couldn't render the lab note template: {message}"),
+            Error::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            Error::NotFound(message) => write!(f, "{message}"),
+            Error::Credential(message) => write!(f, "Credential error: {message}"),
+            Error::Offline(message) => write!(f, "Offline: {message}"),
+            Error::Cancelled => write!(f, "Cancelled."),
+            Error::Prompt(message) => write!(f, "Prompt failed: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Request(error) => Some(error),
+            Error::Json { source, .. } => Some(source),
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Error {
+        Error::Request(error)
+    }
+}
@@ -0,0 +1,30 @@
+//! Builds markdown lab notes from Canvas assignments.
+//!
+//! The [`requester`] module talks to the Canvas REST API through a
+//! [`CanvasClient`], the [`parser`] module turns an assignment description
+//! into questions and markdown (using [`converter`] for general HTML),
+//! [`template`] lays them out as a lab note, [`merge`] carries answers over
+//! when a lab note is regenerated, [`naming`] decides where it's stored, and
+//! [`config`] reads the user's `local.json`, with the token kept in
+//! [`credentials`] (or coming from an [`oauth`] login). Canvas responses
+//! are kept in the [`cache`], so lab notes can be made offline, and
+//! [`sync`] remembers which assignments have already been seen.
+
+pub mod cache;
+pub mod config;
+pub mod converter;
+pub mod credentials;
+pub mod error;
+pub mod merge;
+pub mod naming;
+pub mod oauth;
+pub mod parser;
+pub mod requester;
+pub mod sync;
+pub mod template;
+#[cfg(test)]
+mod test_server;
+
+pub use config::LocalData;
+pub use error::{Error, Result};
+pub use requester::{Assignment, AssignmentGroup, CanvasClient, Course, RetryPolicy, Submission, SubmissionType, User};
@@ -1,208 +1,881 @@
-use inquire::{Confirm, Select};
-use reqwest::{ClientBuilder, Client};
-use serde::Deserialize;
-use std::{fmt, fs, process};
-use std::io::{ErrorKind, Write};
-use std::path::Path;
+use maker::{Assignment, AssignmentGroup, CanvasClient, Course, Error, LocalData, Result, Submission, SubmissionType, User};
+use maker::{credentials, merge, parser, template};
+use maker::merge::{MergeReport, NoteQuestion};
+use maker::requester::SubmissionStatus;
+use maker::parser::{ParsedDescription, QuestionRules};
+use maker::cache::ResponseCache;
+use maker::credentials::CredentialStore;
+use maker::oauth::{self, OAuthConfig, OAuthTokens};
+use maker::naming::NamingPattern;
+use maker::sync::{AssignmentChange, SyncState};
+use maker::template::NoteContext;
+use std::{fs, process};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-mod requester;
-use requester::{Course, Assignment, AssignmentData};
-mod parser;
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use serde::Serialize;
 
-enum MenuOption {
-    NewLabNote,
-    SubmitLabNote,
+mod cli;
+use cli::{AssignmentFilter, AuthCommand, CacheCommand, Cli, Command, ListCommand, NewArgs, OutputFormat, Selection, StatusArgs, SubmitArgs, SyncArgs};
+mod menu;
+use menu::{AssignmentChoice, ExistingFileAction, MenuOption};
+
+// exit codes, so scripts can tell what went wrong. These are listed in `maker --help`.
+const EXIT_USAGE: i32 = 2;
+const EXIT_CONFIG: i32 = 3;
+const EXIT_CANVAS: i32 = 4;
+const EXIT_DATA: i32 = 5;
+const EXIT_FILESYSTEM: i32 = 6;
+const EXIT_CANCELLED: i32 = 130;
+
+// returns false if the user doesn't want the directory.
+fn create_dir(dir: &Path, yes: bool) -> Result<bool> {
+    if !menu::should_create_dir(&dir.display().to_string(), yes) {
+        return Ok(false);
+    }
+    // try and create it that bad boy.
+    fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+    println!("Successfully created directory!");
+    Ok(true)
+}
+
+fn merge_file(path: &Path, content: &str) -> Result<(String, MergeReport)> {
+    let existing: String = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+    Ok(merge::merge_notes(&existing, content))
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    let mut file: fs::File = fs::File::create(path).map_err(|e| Error::io(path, e))?;
+    file.write_all(content.as_bytes()).map_err(|e| Error::io(path, e))
 }
 
-impl fmt::Display for MenuOption {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            MenuOption::NewLabNote => write!(f, "New Lab Note"),
-            MenuOption::SubmitLabNote => write!(f, "Submit Lab Note"),
+fn create_file(path: PathBuf, content: &str, yes: bool, merge: bool, overwrite: bool) -> Result<PathBuf> {
+
+    let mut content: String = content.to_string();
+    if path.is_file() {
+        let action: ExistingFileAction = if merge {
+            ExistingFileAction::Merge
+        } else if overwrite {
+            ExistingFileAction::Overwrite
+        } else {
+            menu::choose_existing_file_action(&path.display().to_string(), yes)
+        };
+        match action {
+            // the user doesn't want to touch it, do nothing.
+            ExistingFileAction::Keep => return Ok(path),
+            ExistingFileAction::Merge => {
+                let (merged, report) = merge_file(&path, &content)?;
+                println!("Merged: {report}.");
+                content = merged;
+            },
+            ExistingFileAction::Overwrite => {},
         }
     }
+
+    write_file(&path, &content)?;
+    Ok(path)
 }
 
-impl MenuOption {
-    fn main_menu() -> MenuOption {
-        println!("--- MAIN MENU ---");
+// returns the lab note file path for an assignment, `assignments` are the others in its course.
+fn get_lab_note_path(local_data: &LocalData, course: &Course, assignment: &Assignment, assignments: &[Assignment]) -> Result<PathBuf> {
+    let pattern: NamingPattern = local_data.get_naming_pattern()?;
+    Ok(local_data.base_dir.join(pattern.get_path(course, assignment, assignments)))
+}
 
-        let main_options: Vec<MenuOption> = vec!{
-            MenuOption::NewLabNote,
-            MenuOption::SubmitLabNote,
-        };
+// the assignments that pass the filter, with whether their lab note exists, by due date.
+// The overdue ones come before the upcoming ones, and the ones without a date last.
+fn get_assignment_choices(local_data: &LocalData, course: &Course, assignments: &CourseAssignments, filter: &AssignmentFilter) -> Result<Vec<AssignmentChoice>> {
+    let pattern: NamingPattern = local_data.get_naming_pattern()?;
+    let now: DateTime<Utc> = Utc::now();
 
-        let answer: MenuOption = Select::new("What would you like to do?", main_options)
-            .prompt()
-            .unwrap_or_else(|_| {
-            println!("Exiting.");
-            process::exit(1);
-        });
+    let mut choices: Vec<AssignmentChoice> = Vec::new();
+    for assignment in assignments.selected.iter() {
+        let has_note: bool = local_data.base_dir.join(pattern.get_path(course, assignment, &assignments.all)).is_file();
+        let is_due_soon: bool = match (filter.due_within, assignment.due_at) {
+            (Some(due_within), Some(due_at)) => due_at >= now && due_at <= now + due_within,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        if (filter.unsubmitted && assignment.is_submitted()) || (filter.missing_note && has_note) || !is_due_soon {
+            continue;
+        }
+        choices.push(AssignmentChoice::new(assignment.clone(), has_note));
+    }
+    choices.sort_by_key(|x| (x.assignment.due_at.is_none(), x.assignment.due_at));
+    Ok(choices)
+}
 
-        answer
+// the choices for picking one assignment, where the filters leaving none is worth explaining.
+fn get_choices_to_pick(local_data: &LocalData, course: &Course, assignments: &CourseAssignments, filter: &AssignmentFilter) -> Result<Vec<AssignmentChoice>> {
+    let choices: Vec<AssignmentChoice> = get_assignment_choices(local_data, course, assignments, filter)?;
+    let is_filtered: bool = filter.unsubmitted || filter.missing_note || filter.due_within.is_some();
+    if choices.is_empty() && is_filtered && !assignments.selected.is_empty() {
+        return Err(Error::NotFound(format!("No assignments in {} match the filters", course.name)));
     }
+    Ok(choices)
 }
 
-#[derive(Deserialize)]
-struct LocalData {
-    name: String,
-    token: String,
-    base_dir: String,
+// the courses to choose from, filtered by the config.
+async fn get_courses(client: &CanvasClient, local_data: &LocalData) -> Result<Vec<Course>> {
+    let courses: Vec<Course> = Course::get_courses(client, local_data.all_courses).await?;
+    Ok(local_data.filter_courses(courses))
 }
 
-impl LocalData {
-    fn get_local_data() -> LocalData {
+// a course's assignments. Lab note paths are resolved against all of them,
+// so an assignment gets the same path whichever group it was picked from.
+struct CourseAssignments {
+    // the ones in the course's lab groups.
+    selected: Vec<Assignment>,
+    // every assignment in the course.
+    all: Vec<Assignment>,
+    // none of the course's groups matched and nobody was asked, so `selected` is every assignment too.
+    is_fallback: bool,
+}
 
-        let local_file: String = String::from("local.json");
+// the assignments in the course's lab groups. If none match, asks for a group,
+// or without `can_ask` (or offline, like `maker cache sync`) takes every assignment.
+async fn get_assignments(client: &CanvasClient, local_data: &LocalData, course: &Course, filter: &AssignmentFilter, can_ask: bool) -> Result<CourseAssignments> {
+    let can_ask: bool = can_ask && !client.is_offline();
+    // the whole course is one request, the groups are picked out of it.
+    let all: Vec<Assignment> = Assignment::get_assignments(client, course.id, None).await?;
+    if filter.all_groups || local_data.is_all_groups(course.id) {
+        return Ok(CourseAssignments { selected: all.clone(), all, is_fallback: false });
+    }
 
-        // open the JSON file and read it into a string.
-        let local_data: String = fs::read_to_string(&local_file)
-            .unwrap_or_else(|error| {
-            if error.kind() == ErrorKind::NotFound {
-                println!("File `{local_file}` not found!: {error}");
-                println!("\nPlease create a `{local_file}` file with this format:
-                \n{{
-                    \n\t\"name\": \"<your name here>\",
-                    \n\t\"token\": \"<token from canvas here>\",
-                    \n\t\"base_dir\": \"<full directory path for storing lab file here>\"
-                \n}}");
-                process::exit(1);
-            } else {
-                println!("Problem opening the file: {error:?}");
-                process::exit(1);
-            }
-        });
-
-        // make sure the JSON is the correct format.
-        serde_json::from_str(&local_data).unwrap_or_else(|error| {
-            println!("Invalid JSON format in `{local_file}`: {error}.");
-            println!("\nPlease make sure the `{local_file}` file is formatted like this: 
-            \n{{
-                \n\t\"name\": \"<your name here>\",
-                \n\t\"token\": \"<token from canvas here>\",
-                \n\t\"base_dir\": \"<full directory path for storing lab file here>\"
-                \n}}");
-            process::exit(1);
-        })
+    let groups: Vec<AssignmentGroup> = AssignmentGroup::get_groups(client, course.id).await?;
+    let group_ids: Vec<i64> = AssignmentGroup::find_groups(&groups, &local_data.get_group_patterns(course.id)?)
+        .iter()
+        .map(|x| x.id)
+        .collect();
+    let is_fallback: bool = group_ids.is_empty() && !groups.is_empty() && !can_ask;
+    // `None` is every assignment in the course.
+    let group_ids: Option<Vec<i64>> = if !group_ids.is_empty() {
+        Some(group_ids)
+    } else if groups.is_empty() || !can_ask {
+        None
+    } else {
+        menu::choose_assignment_group(groups)?.map(|x| vec!{x.id})
+    };
+
+    let selected: Vec<Assignment> = match group_ids {
+        Some(group_ids) => all.iter()
+            .filter(|x| x.assignment_group_id.is_some_and(|id| group_ids.contains(&id)))
+            .cloned()
+            .collect(),
+        None => all.clone(),
+    };
+    Ok(CourseAssignments { selected, all, is_fallback })
+}
+
+// what happened to one assignment's lab note in `maker new --all`.
+enum NoteOutcome {
+    Created(PathBuf),
+    Merged(PathBuf, MergeReport),
+    Skipped(String),
+    Failed(Error),
+}
+
+impl NoteOutcome {
+    fn get_status(&self) -> &str {
+        match self {
+            NoteOutcome::Created(_) => "created",
+            NoteOutcome::Merged(_, _) => "merged",
+            NoteOutcome::Skipped(_) => "skipped",
+            NoteOutcome::Failed(_) => "failed",
+        }
     }
+
+    fn get_detail(&self) -> String {
+        match self {
+            NoteOutcome::Created(path) => path.display().to_string(),
+            NoteOutcome::Merged(path, report) => format!("{}, {report}", path.display()),
+            NoteOutcome::Skipped(reason) => reason.clone(),
+            NoteOutcome::Failed(error) => error.to_string(),
+        }
+    }
+}
+
+// returns the chosen course and assignment, and every assignment in the course.
+async fn choose_course_and_assignment(client: &CanvasClient, local_data: &LocalData, selection: &Selection) -> Result<(Course, Assignment, Vec<Assignment>)> {
+    let course: Course = menu::choose_course(get_courses(client, local_data).await?, selection.course.as_deref())?;
+    let assignments: CourseAssignments = get_assignments(client, local_data, &course, &selection.filter, true).await?;
+    let choices: Vec<AssignmentChoice> = get_choices_to_pick(local_data, &course, &assignments, &selection.filter)?;
+    let assignment: Assignment = menu::choose_assignment(choices, selection.assignment.as_deref())?;
+    Ok((course, assignment, assignments.all))
 }
 
-fn should_create_dir(path: &str) -> bool {
-    let ans = Confirm::new(&format!("{path} directory doesn't exist, do you want to create it?"))
-        .with_default(true)
-        .prompt();
-    matches!(ans, Ok(true))
+// the lab note's content for an assignment.
+fn render_lab_note(local_data: &LocalData, course: &Course, assignment: &Assignment, full_description: bool) -> Result<String> {
+    let rules: QuestionRules = local_data.get_question_rules(course.id)?;
+    let parsed: ParsedDescription = parser::parse_description(assignment.get_description()?, &rules)?;
+    let template: String = match local_data.get_template_path(course.id) {
+        Some(path) => template::load_template(path)?,
+        None => String::from(template::DEFAULT_TEMPLATE),
+    };
+    let context: NoteContext = NoteContext::new(course, assignment, &parsed, &local_data.name, full_description);
+    template::render(&template, &context)
 }
 
-fn create_dir(dir: &Path, content: &str) {
-    let path_name = dir.to_str().unwrap_or("");
+async fn handle_new_lab_note(client: &CanvasClient, local_data: &LocalData, args: &NewArgs, yes: bool) -> Result<Option<PathBuf>> {
+    let (course, assignment, assignments) = choose_course_and_assignment(client, local_data, &args.selection).await?;
+
+    let full_description: bool = args.full || local_data.full_description;
+    let content: String = render_lab_note(local_data, &course, &assignment, full_description)?;
+    if args.stdout {
+        print!("{content}");
+        return Ok(None);
+    }
+    let file_path: PathBuf = get_lab_note_path(local_data, &course, &assignment, &assignments)?;
 
-    match should_create_dir(path_name) {
-        // try and create it that bad boy.
-        true => {
-            fs::create_dir_all(dir).unwrap_or_else(|e| {
-                // error creating the dir, just print the content.
-                println!("Error creating directory: {e}");
+    let dir: &Path = file_path.parent().unwrap_or(&local_data.base_dir);
+    // dir doesn't exist, create it. If we can't, just print the content.
+    if !dir.is_dir() {
+        match create_dir(dir, yes) {
+            Ok(true) => {},
+            Ok(false) => {
+                println!("lab note content:\n{content}");
+                return Ok(None);
+            },
+            Err(error) => {
                 println!("lab note content:\n{content}");
-                process::exit(1);
-            });
-            println!("Successfully created directory!");
+                return Err(error);
+            },
+        }
+    }
+
+    create_file(file_path, &content, yes, args.merge, args.overwrite).map(Some).inspect_err(|_| {
+        // error writing the file, just print the content.
+        println!("lab note content:\n{content}");
+    })
+}
+
+// writes one assignment's lab note for `maker new --all`, never overwriting one.
+fn create_batch_note(local_data: &LocalData, course: &Course, assignment: &Assignment, assignments: &[Assignment], full: bool, merge: bool, yes: bool) -> Result<NoteOutcome> {
+    let path: PathBuf = get_lab_note_path(local_data, course, assignment, assignments)?;
+    if path.is_file() && !merge {
+        return Ok(NoteOutcome::Skipped(format!("{} already exists", path.display())));
+    }
+    let content: String = render_lab_note(local_data, course, assignment, full || local_data.full_description)?;
+
+    if path.is_file() {
+        let (merged, report) = merge_file(&path, &content)?;
+        write_file(&path, &merged)?;
+        return Ok(NoteOutcome::Merged(path, report));
+    }
+    let dir: &Path = path.parent().unwrap_or(&local_data.base_dir);
+    if !dir.is_dir() && !create_dir(dir, yes)? {
+        return Ok(NoteOutcome::Skipped(format!("{} wasn't created", dir.display())));
+    }
+    write_file(&path, &content)?;
+    Ok(NoteOutcome::Created(path))
+}
+
+// creates the lab notes for every assignment in a course, then prints what happened to each.
+// The assignment list already has every description, so this is one request however many notes there are.
+async fn handle_new_all_lab_notes(client: &CanvasClient, local_data: &LocalData, args: &NewArgs, yes: bool) -> Result<()> {
+    let selection: &Selection = &args.selection;
+    let course: Course = menu::choose_course(get_courses(client, local_data).await?, selection.course.as_deref())?;
+    let assignments: CourseAssignments = get_assignments(client, local_data, &course, &selection.filter, true).await?;
+    let choices: Vec<AssignmentChoice> = get_assignment_choices(local_data, &course, &assignments, &selection.filter)?;
+
+    let mut outcomes: Vec<(String, NoteOutcome)> = Vec::new();
+    for choice in choices {
+        let assignment: Assignment = choice.assignment;
+        let outcome: NoteOutcome = create_batch_note(local_data, &course, &assignment, &assignments.all, args.full, args.merge, yes)
+            .unwrap_or_else(NoteOutcome::Failed);
+        outcomes.push((assignment.name, outcome));
+    }
+
+    let name_width: usize = outcomes.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+    for (name, outcome) in outcomes.iter() {
+        println!("{:<7}  {name:<name_width$}  {}", outcome.get_status(), outcome.get_detail());
+    }
+    let count = |status: &str| outcomes.iter().filter(|(_, x)| x.get_status() == status).count();
+    println!(
+        "\n{} created, {} merged, {} skipped, {} failed.",
+        count("created"), count("merged"), count("skipped"), count("failed"),
+    );
+
+    // the table already shows every failure, the exit code comes from the first.
+    match outcomes.into_iter().find_map(|(_, x)| if let NoteOutcome::Failed(error) = x { Some(error) } else { None }) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+async fn handle_submit_lab_note(client: &CanvasClient, local_data: &LocalData, args: &SubmitArgs, yes: bool) -> Result<()> {
+    let (course, assignment, assignments) = choose_course_and_assignment(client, local_data, &args.selection).await?;
+    let path: PathBuf = get_lab_note_path(local_data, &course, &assignment, &assignments)?;
+
+    if !path.is_file() {
+        return Err(Error::NotFound(format!("No lab note found at {}", path.display())));
+    }
+
+    let submission_types: Vec<SubmissionType> = SubmissionType::get_supported_types(&assignment);
+    if submission_types.is_empty() {
+        return Err(Error::NotFound(format!("{} doesn't accept text entry or `.md` file upload submissions.", assignment.name)));
+    }
+    let submission_type: SubmissionType = match args.submit_type {
+        Some(submit_type) if submission_types.contains(&submit_type.into()) => submit_type.into(),
+        Some(submit_type) => {
+            let submission_type: SubmissionType = submit_type.into();
+            return Err(Error::NotFound(format!("{} doesn't accept {submission_type} submissions.", assignment.name)));
         },
-        // don't try to create it, just print the content.
-        false => {
-            println!("lab note content:\n{content}");
-            process::exit(0);
+        None => menu::choose_submission_type(submission_types)?,
+    };
+
+    if !menu::should_submit(&path.display().to_string(), &assignment.name, yes) {
+        println!("Exiting.");
+        return Ok(());
+    }
+
+    let submission: Submission = Submission::submit_lab_note(client, course.id, assignment.id, submission_type, &path).await?;
+    println!("Submitted! Submission ID: {}", submission.id);
+    if let Some(submitted_at) = submission.submitted_at {
+        println!("Submitted at: {submitted_at}");
+    }
+    Ok(())
+}
+
+async fn handle_list(client: &CanvasClient, local_data: &LocalData, command: &ListCommand) -> Result<()> {
+    match command {
+        ListCommand::Courses => {
+            for course in get_courses(client, local_data).await? {
+                let term: &str = course.term.as_ref().map(|x| x.name.as_str()).unwrap_or_default();
+                println!("{}\t{}\t{term}", course.id, course.name);
+            }
         },
+        ListCommand::Assignments { course, filter } => {
+            let course: Course = menu::choose_course(get_courses(client, local_data).await?, course.as_deref())?;
+            let assignments: CourseAssignments = get_assignments(client, local_data, &course, filter, true).await?;
+            for choice in get_choices_to_pick(local_data, &course, &assignments, filter)? {
+                let assignment: &Assignment = &choice.assignment;
+                let due_date: String = assignment.get_local_due_date().unwrap_or_default();
+                let points: String = assignment.get_points().unwrap_or_default();
+                let submitted: &str = if assignment.is_submitted() { "submitted" } else { "" };
+                let note: &str = if choice.has_note { "note" } else { "" };
+                println!("{}\t{}\t{due_date}\t{points}\t{submitted}\t{note}", assignment.id, assignment.name);
+            }
+        },
+    }
+    Ok(())
+}
+
+fn handle_cache_clear() -> Result<()> {
+    let cache: ResponseCache = ResponseCache::open_default()?;
+    let count: usize = cache.clear()?;
+    println!("Deleted {count} cached responses from {}.", cache.dir().display());
+    Ok(())
+}
+
+// fetches what making a lab note needs, which also stores it in the cache.
+async fn handle_cache_sync(client: &CanvasClient, local_data: &LocalData, course: Option<&str>) -> Result<()> {
+    let courses: Vec<Course> = get_courses(client, local_data).await?;
+    let courses: Vec<Course> = match course {
+        Some(query) => vec!{menu::choose_course(courses, Some(query))?},
+        None => courses,
+    };
+    for course in courses.iter() {
+        let assignments: CourseAssignments = get_assignments(client, local_data, course, &AssignmentFilter::default(), false).await?;
+        println!("{}: {} assignments", course.name, assignments.selected.len());
     }
+    println!("Cached {} courses, `--offline` can use them now.", courses.len());
+    Ok(())
 }
 
-fn should_overwrite_file(file_path: &str) -> bool {
-    let ans = Confirm::new(&format!("{file_path} already exists, do you want to overwrite it?"))
-        .with_default(false)
-        .prompt();
-    matches!(ans, Ok(true))
+// something `maker sync` found, handed to the notification and the hook.
+struct SyncEvent {
+    kind: &'static str,
+    course: String,
+    assignment: Assignment,
+    note: Option<PathBuf>,
 }
 
-fn create_file(file_path: String, content: &str) -> Option<String> {
+// one pass: creates the notes for assignments it hasn't seen, reports the ones whose description changed.
+async fn sync_once(client: &CanvasClient, local_data: &LocalData, course: Option<&str>, yes: bool) -> Result<Vec<SyncEvent>> {
+    let courses: Vec<Course> = get_courses(client, local_data).await?;
+    let courses: Vec<Course> = match course {
+        Some(query) => vec!{menu::choose_course(courses, Some(query))?},
+        None => courses,
+    };
+    let state_file: PathBuf = SyncState::get_state_file(&local_data.base_dir);
+    let mut state: SyncState = SyncState::load(&state_file)?;
 
-    let path: &Path = Path::new(&file_path);
-    // file already exists and the user doesn't want to overwrite it, do nothing.
-    if path.is_file() && !should_overwrite_file(&file_path) {
-        return Some(file_path);
+    let mut events: Vec<SyncEvent> = Vec::new();
+    for course in courses.iter() {
+        let assignments: CourseAssignments = get_assignments(client, local_data, course, &AssignmentFilter::default(), false).await?;
+        // every quiz and exam would get a lab note.
+        if assignments.is_fallback {
+            println!("skipped  {}: none of its assignment groups match `assignment_groups`, set it or `all_groups` for the course", course.name);
+            continue;
+        }
+        for assignment in assignments.selected.iter() {
+            match state.get_change(assignment) {
+                AssignmentChange::New => match create_batch_note(local_data, course, assignment, &assignments.all, false, false, yes) {
+                    Ok(NoteOutcome::Created(path)) => {
+                        println!("new      {}: {} -> {}", course.name, assignment.name, path.display());
+                        events.push(SyncEvent { kind: "new", course: course.name.clone(), assignment: assignment.clone(), note: Some(path) });
+                    },
+                    Ok(NoteOutcome::Skipped(reason)) => {
+                        let has_note: bool = get_lab_note_path(local_data, course, assignment, &assignments.all).is_ok_and(|x| x.is_file());
+                        // already has a note, nothing new to tell. If it wasn't written, it's tried again next time.
+                        if !has_note {
+                            println!("skipped  {}: {}: {reason}", course.name, assignment.name);
+                            continue;
+                        }
+                    },
+                    Ok(_) => {},
+                    // not remembered, so it's tried again next time (a locked assignment may open up).
+                    Err(error) => {
+                        println!("failed   {}: {}: {error}", course.name, assignment.name);
+                        continue;
+                    },
+                },
+                AssignmentChange::DescriptionChanged => {
+                    println!("changed  {}: {}, run `maker new -c {} -a {} --merge` to update the note", course.name, assignment.name, course.id, assignment.id);
+                    let note: Option<PathBuf> = get_lab_note_path(local_data, course, assignment, &assignments.all).ok().filter(|x| x.is_file());
+                    events.push(SyncEvent { kind: "changed", course: course.name.clone(), assignment: assignment.clone(), note });
+                },
+                AssignmentChange::Unchanged => {},
+            }
+            state.update(course.id, assignment);
+        }
+        // saved per course, so a failure later on doesn't lose what's done.
+        state.save(&state_file)?;
     }
+    Ok(events)
+}
 
-    let mut file: fs::File = fs::File::create(path).unwrap_or_else(|e| {
-        // error creating the file, just print the content.
-        println!("Error creating file: {e}");
-        println!("lab note content:\n{content}");
-        process::exit(1);
-    });
+// a desktop notification, if the system has a way to show one.
+fn send_notification(title: &str, body: &str) {
+    let result = if cfg!(target_os = "macos") {
+        let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        process::Command::new("osascript")
+            .arg("-e")
+            .arg(format!("display notification \"{}\" with title \"{}\"", escape(body), escape(title)))
+            .status()
+    } else {
+        process::Command::new("notify-send").arg(title).arg(body).status()
+    };
+    if result.is_err() {
+        eprintln!("Couldn't show a notification.");
+    }
+}
 
-    if let Err(e) = file.write_all(content.as_bytes()) {
-        // error writing to the file, just print the content.
-        println!("Error writing to the file: {e}");
-        println!("lab note content:\n{content}");
-        process::exit(1);
+// runs the hook once for the event, the details are in environment variables.
+fn run_sync_hook(hook: &[String], event: &SyncEvent) {
+    let note: String = event.note.as_ref().map(|x| x.display().to_string()).unwrap_or_default();
+    let result = process::Command::new(&hook[0])
+        .args(&hook[1..])
+        .env("MAKER_EVENT", event.kind)
+        .env("MAKER_COURSE", &event.course)
+        .env("MAKER_ASSIGNMENT", &event.assignment.name)
+        .env("MAKER_ASSIGNMENT_ID", event.assignment.id.to_string())
+        .env("MAKER_URL", event.assignment.html_url.as_deref().unwrap_or_default())
+        .env("MAKER_NOTE", note)
+        .status();
+    match result {
+        Ok(status) if !status.success() => eprintln!("The sync hook exited with {status}"),
+        Ok(_) => {},
+        Err(error) => eprintln!("Couldn't run the sync hook `{}`: {error}", hook[0]),
+    }
+}
+
+fn handle_sync_events(events: &[SyncEvent], notify: bool, hook: Option<&[String]>) {
+    if events.is_empty() {
+        return;
+    }
+    if notify {
+        let count = |kind: &str| events.iter().filter(|x| x.kind == kind).count();
+        let names: Vec<&str> = events.iter().map(|x| x.assignment.name.as_str()).collect();
+        let title: String = format!("maker: {} new, {} changed", count("new"), count("changed"));
+        send_notification(&title, &names.join(", "));
+    }
+    if let Some(hook) = hook {
+        for event in events.iter() {
+            run_sync_hook(hook, event);
+        }
+    }
+}
+
+async fn handle_sync(client: &CanvasClient, local_data: &LocalData, args: &SyncArgs, yes: bool) -> Result<()> {
+    let notify: bool = args.notify || local_data.sync.notify;
+    let hook: Option<Vec<String>> = match args.hook.as_ref().or(local_data.sync.hook.as_ref()) {
+        Some(hook) => {
+            let command: Vec<String> = shell_words::split(hook)
+                .map_err(|error| Error::Config(format!("Invalid sync hook `{hook}`: {error}")))?;
+            if command.is_empty() {
+                return Err(Error::Config(format!("Invalid sync hook `{hook}`")));
+            }
+            Some(command)
+        },
+        None => None,
     };
+    // `--interval` is always more than 0.
+    let interval: Duration = args.interval.to_std().unwrap_or(Duration::from_secs(900));
 
-    Some(file_path)
+    loop {
+        match sync_once(client, local_data, args.course.as_deref(), yes).await {
+            Ok(events) => handle_sync_events(&events, notify, hook.as_deref()),
+            // one failed check (like the wifi dropping) shouldn't end the watch.
+            Err(error) if args.watch => eprintln!("Error: {error}"),
+            Err(error) => return Err(error),
+        }
+        if !args.watch {
+            return Ok(());
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {},
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
 }
 
-async fn handle_new_lab_note(client: &Client, local_data: &LocalData) -> Option<String> {
-    let course: Course = Course::get_course(client, &local_data.token).await;
-    let assignment: Assignment = Assignment::get_assignment(client, &local_data.token, course.id).await;
-    let assignment_data: AssignmentData = AssignmentData::get_assignment_data(client, &local_data.token, course.id, assignment.id).await;
-    
-    let content: String = parser::create_markdown(&assignment_data.description, &local_data.name, assignment.name.clone());
-    let course_dir: String = local_data.base_dir.to_owned() + &course.name[..7].trim().to_lowercase().replace(' ', "") + "/lab/";
-    let file_name: String = assignment.name.trim().to_lowercase().replace(' ', "_") + ".md";
+// one assignment in `maker status`.
+#[derive(Serialize)]
+struct StatusRow {
+    course: String,
+    course_id: i64,
+    assignment: String,
+    assignment_id: i64,
+    due_at: Option<DateTime<Utc>>,
+    note: Option<PathBuf>,
+    questions: Option<usize>,
+    unanswered: Option<usize>,
+    submitted: bool,
+    grade: Option<String>,
+    score: Option<f64>,
+    points_possible: Option<f64>,
+    // how the table and the csv show them.
+    #[serde(skip)]
+    due_date: Option<String>,
+    #[serde(skip)]
+    points: Option<String>,
+}
 
-    let dir: &Path = Path::new(&course_dir);
-    // dir doesn't exist, create it.
-    if !dir.is_dir() {
-        create_dir(dir, &content);
+impl StatusRow {
+    fn new(course: &Course, assignment: &Assignment, note: Option<PathBuf>) -> Result<StatusRow> {
+        // questions without anything written under them, leaving out the ones removed from the assignment.
+        let (questions, unanswered) = match &note {
+            Some(path) => {
+                let content: String = fs::read_to_string(path).map_err(|error| Error::io(path, error))?;
+                let questions: Vec<NoteQuestion> = merge::parse_questions(&content).into_iter()
+                    .filter(|x| x.number.is_some())
+                    .collect();
+                let unanswered: usize = questions.iter().filter(|x| x.answer.trim().is_empty()).count();
+                (Some(questions.len()), Some(unanswered))
+            },
+            None => (None, None),
+        };
+        let submission: Option<&SubmissionStatus> = assignment.submission.as_ref();
+        Ok(StatusRow {
+            course: course.name.clone(),
+            course_id: course.id,
+            assignment: assignment.name.clone(),
+            assignment_id: assignment.id,
+            due_at: assignment.due_at,
+            note,
+            questions,
+            unanswered,
+            submitted: assignment.is_submitted(),
+            grade: submission.and_then(|x| x.grade.clone()),
+            score: submission.and_then(|x| x.score),
+            points_possible: assignment.points_possible,
+            due_date: assignment.get_local_due_date(),
+            points: assignment.get_points(),
+        })
     }
 
-    let file_path: String = format!("{course_dir}/{file_name}");
+    // the columns, as the table and the csv show them.
+    fn get_columns(&self) -> [String; 8] {
+        [
+            self.course.clone(),
+            self.assignment.clone(),
+            self.due_date.clone().unwrap_or_default(),
+            String::from(if self.note.is_some() { "yes" } else { "no" }),
+            match (self.unanswered, self.questions) {
+                (Some(unanswered), Some(questions)) => format!("{unanswered}/{questions}"),
+                _ => String::new(),
+            },
+            String::from(if self.submitted { "yes" } else { "no" }),
+            self.grade.clone().unwrap_or_default(),
+            self.points.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+const STATUS_HEADERS: [&str; 8] = ["course", "assignment", "due", "note", "unanswered", "submitted", "grade", "points"];
 
-    create_file(file_path, &content)
+fn print_status_table(rows: &[StatusRow]) {
+    let columns: Vec<[String; 8]> = rows.iter().map(StatusRow::get_columns).collect();
+    let mut widths: [usize; 8] = STATUS_HEADERS.map(str::len);
+    for row in columns.iter() {
+        for (width, column) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+    let print_row = |row: &[&str]| {
+        let line: Vec<String> = row.iter().zip(widths.iter()).map(|(x, width)| format!("{x:<width$}")).collect();
+        println!("{}", line.join("  ").trim_end());
+    };
+    print_row(&STATUS_HEADERS);
+    for row in columns.iter() {
+        print_row(&row.each_ref().map(String::as_str));
+    }
 }
 
-#[tokio::main]
-async fn main() {
-    let local_data: LocalData = LocalData::get_local_data();
-    if local_data.base_dir.contains('~') {
-        println!("Please use the full path to the lab note directory");
-        process::exit(1);
-    }
-    // create the client
-    let timeout: Duration = Duration::new(5, 0);
-    let client: Client = ClientBuilder::new()
-        .timeout(timeout)
-        .build()
-        .unwrap_or_else(|error| {
-        println!("Error builing the client: {error:?}");
-        process::exit(1);
-    });
-
-    let option: MenuOption = MenuOption::main_menu();
-    match option {
-        MenuOption::NewLabNote => {
-            if let Some(file_path) = handle_new_lab_note(&client, &local_data).await {
-                std::process::Command::new("/usr/bin/sh")
-                    .arg("-c")
-                    .arg(format!("vim {file_path}"))
-                    .spawn()
-                    .expect("Error: Failed to run editor")
-                    .wait()
-                    .expect("Error: Editor returned a non-zero status");
+// quotes a csv field if it has anything that would break the row.
+fn get_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn print_status_csv(rows: &[StatusRow]) {
+    println!("{}", STATUS_HEADERS.join(","));
+    for row in rows.iter() {
+        let fields: Vec<String> = row.get_columns().iter().map(|x| get_csv_field(x)).collect();
+        println!("{}", fields.join(","));
+    }
+}
+
+// every markdown file under `dir`, to find the notes no assignment points to.
+fn find_notes(dir: &Path, notes: &mut Vec<PathBuf>) -> Result<()> {
+    let entries: fs::ReadDir = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(Error::io(dir, error)),
+    };
+    for entry in entries {
+        let path: PathBuf = entry.map_err(|error| Error::io(dir, error))?.path();
+        if path.is_dir() {
+            find_notes(&path, notes)?;
+        } else if path.extension().is_some_and(|x| x == "md") {
+            notes.push(path);
+        }
+    }
+    Ok(())
+}
+
+async fn handle_status(client: &CanvasClient, local_data: &LocalData, args: &StatusArgs) -> Result<()> {
+    let courses: Vec<Course> = get_courses(client, local_data).await?;
+    let courses: Vec<Course> = match &args.course {
+        Some(query) => vec!{menu::choose_course(courses, Some(query))?},
+        None => courses,
+    };
+
+    let pattern: NamingPattern = local_data.get_naming_pattern()?;
+    let mut rows: Vec<StatusRow> = Vec::new();
+    let mut known_notes: Vec<PathBuf> = Vec::new();
+    for course in courses.iter() {
+        let assignments: CourseAssignments = get_assignments(client, local_data, course, &AssignmentFilter::default(), false).await?;
+        for assignment in assignments.all.iter() {
+            known_notes.push(local_data.base_dir.join(pattern.get_path(course, assignment, &assignments.all)));
+        }
+        // every quiz and exam would be listed, and stdout may be json or csv.
+        if assignments.is_fallback {
+            eprintln!("skipped  {}: none of its assignment groups match `assignment_groups`, set it or `all_groups` for the course", course.name);
+            continue;
+        }
+        for choice in get_assignment_choices(local_data, course, &assignments, &args.filter)? {
+            let note: Option<PathBuf> = Some(local_data.base_dir.join(pattern.get_path(course, &choice.assignment, &assignments.all)))
+                .filter(|x| x.is_file());
+            rows.push(StatusRow::new(course, &choice.assignment, note)?);
+        }
+    }
+
+    match args.format {
+        OutputFormat::Json => {
+            let json: String = serde_json::to_string_pretty(&rows).map_err(|error| Error::json("the status", error))?;
+            println!("{json}");
+        },
+        OutputFormat::Csv => print_status_csv(&rows),
+        OutputFormat::Table => {
+            print_status_table(&rows);
+            // notes of other courses would all look lost.
+            if args.course.is_none() {
+                let mut notes: Vec<PathBuf> = Vec::new();
+                find_notes(&local_data.base_dir, &mut notes)?;
+                notes.retain(|x| !known_notes.contains(x));
+                if !notes.is_empty() {
+                    println!("\nNotes without an assignment:");
+                    notes.sort();
+                    for note in notes.iter() {
+                        println!("  {}", note.display());
+                    }
+                }
+            }
+        },
+    }
+    Ok(())
+}
+
+// prints the login url, and tries to open it. If that doesn't work, the user can open it themselves.
+fn open_browser(url: &str) {
+    println!("Log in to Canvas at:\n{url}");
+    let opener: &str = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    let _ = process::Command::new(opener)
+        .arg(url)
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null())
+        .spawn();
+}
+
+fn read_token_from_stdin() -> Result<String> {
+    let mut token: String = String::new();
+    io::stdin().read_line(&mut token).map_err(|e| Error::io("stdin", e))?;
+    Ok(token.trim().to_string())
+}
+
+async fn handle_auth(local_data: &LocalData, command: &AuthCommand) -> Result<()> {
+    match command {
+        AuthCommand::Login { name, file, token_stdin, oauth } => {
+            // what's stored, and the access token to check it with.
+            let (stored, token): (String, String) = if *oauth {
+                let config: &OAuthConfig = local_data.oauth.as_ref().ok_or_else(|| {
+                    Error::Config(format!("Set `oauth` with your developer key in {} first", local_data.config_file.display()))
+                })?;
+                let tokens: OAuthTokens = oauth::login(&local_data.canvas_url, config, local_data.get_timeout()?, open_browser).await?;
+                (tokens.to_stored()?, tokens.access_token)
+            } else {
+                let token: String = if *token_stdin { read_token_from_stdin()? } else { menu::get_token_input()? };
+                (token.clone(), token)
+            };
+            if token.is_empty() {
+                return Err(Error::Config(String::from("The token is empty")));
+            }
+            // only store a token that works.
+            let client: CanvasClient = local_data.new_client(&token)?;
+            let user: User = User::get_self(&client).await?;
+
+            let name: String = name.clone().unwrap_or_else(|| local_data.get_credential_name());
+            let store: CredentialStore = credentials::save_token(&name, &stored, *file, menu::create_passphrase).await?;
+            println!("Logged in to {} as {}, the token is stored in {store} as `{name}`.", local_data.canvas_url, user.name);
+
+            if name != local_data.get_credential_name() {
+                println!("Set `\"credential\": \"{name}\"` in {} to use it.", local_data.config_file.display());
+            }
+            if local_data.token.is_some() {
+                println!("`token` is still set in {} (or $MAKER_TOKEN), remove it to use the stored token.", local_data.config_file.display());
+            }
+        },
+        AuthCommand::Status => {
+            let source: String = match &local_data.token {
+                Some(_) => format!("token from {} (or $MAKER_TOKEN)", local_data.config_file.display()),
+                None => {
+                    let name: String = local_data.get_credential_name();
+                    match credentials::load_token(&name, menu::get_passphrase).await? {
+                        Some((stored, store)) => {
+                            let kind: &str = if OAuthTokens::from_stored(&stored).is_some() { "OAuth login" } else { "token" };
+                            format!("{kind} stored in {store} as `{name}`")
+                        },
+                        None => return Err(Error::Config(format!("Not logged in, no token stored as `{name}`. Run `maker auth login`"))),
+                    }
+                },
+            };
+            let client: CanvasClient = local_data.get_client(menu::get_passphrase).await?;
+            let user: User = User::get_self(&client).await?;
+            println!("Logged in to {} as {} (id {}).", local_data.canvas_url, user.name, user.id);
+            println!("Using the {source}.");
+        },
+    }
+    Ok(())
+}
+
+fn open_editor(local_data: &LocalData, file_path: &Path) -> Result<()> {
+    let command: Vec<String> = local_data.get_editor_command()?;
+    // the path is its own argument, so spaces in it are fine.
+    let status = process::Command::new(&command[0])
+        .args(&command[1..])
+        .arg(file_path)
+        .status()
+        .map_err(|error| Error::io(&command[0], error))?;
+    if !status.success() {
+        println!("Editor exited with {status}");
+    }
+    Ok(())
+}
+
+fn get_exit_code(error: &Error) -> i32 {
+    match error {
+        Error::Config(_) | Error::Template(_) | Error::Credential(_) => EXIT_CONFIG,
+        Error::Request(_) | Error::Status { .. } | Error::Offline(_) => EXIT_CANVAS,
+        Error::Json { .. } | Error::Html(_) | Error::NotFound(_) => EXIT_DATA,
+        Error::Io { .. } => EXIT_FILESYSTEM,
+        Error::Cancelled => EXIT_CANCELLED,
+        Error::Prompt(_) => EXIT_USAGE,
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let mut local_data: LocalData = LocalData::get_local_data(cli.config.as_deref(), cli.profile.as_deref())?;
+    if cli.term.is_some() {
+        local_data.term = cli.term.clone();
+    }
+    local_data.all_courses |= cli.all_courses;
+
+    // no command, ask what to do.
+    let command: Command = match cli.command {
+        Some(command) => command,
+        None => match MenuOption::main_menu()? {
+            MenuOption::NewLabNote => Command::New(NewArgs::default()),
+            MenuOption::SubmitLabNote => Command::Submit(SubmitArgs::default()),
+        },
+    };
+    // logging in doesn't need a token yet, and clearing the cache doesn't need one at all.
+    match &command {
+        Command::Auth(command) => return handle_auth(&local_data, command).await,
+        Command::Cache(CacheCommand::Clear) => return handle_cache_clear(),
+        _ => {},
+    }
+
+    let client: CanvasClient = if cli.offline {
+        local_data.get_offline_client()?
+    } else {
+        local_data.get_client(menu::get_passphrase).await?
+    };
+
+    match command {
+        Command::New(args) if args.all => handle_new_all_lab_notes(&client, &local_data, &args, cli.yes).await?,
+        Command::New(args) => {
+            if let Some(file_path) = handle_new_lab_note(&client, &local_data, &args, cli.yes).await? {
+                if !args.no_edit {
+                    open_editor(&local_data, &file_path)?;
+                }
             }
         },
-        _ => {
-            println!("Sorry! Working on the implementation for this...");
+        Command::Submit(args) => {
+            handle_submit_lab_note(&client, &local_data, &args, cli.yes).await?;
+        },
+        Command::List(command) => {
+            handle_list(&client, &local_data, &command).await?;
         },
-    }   
-    
+        Command::Status(args) => handle_status(&client, &local_data, &args).await?,
+        Command::Sync(args) => handle_sync(&client, &local_data, &args, cli.yes).await?,
+        Command::Cache(CacheCommand::Sync { course }) => {
+            if cli.offline {
+                return Err(Error::Offline(String::from("Syncing the cache needs Canvas, run it without `--offline`")));
+            }
+            handle_cache_sync(&client, &local_data, course.as_deref()).await?;
+        },
+        Command::Auth(_) | Command::Cache(CacheCommand::Clear) => {},
+    }
+    Ok(())
 }
 
+#[tokio::main]
+async fn main() {
+    let cli: Cli = Cli::parse();
+    if let Err(error) = run(cli).await {
+        match error {
+            Error::Cancelled => println!("Exiting."),
+            ref other => eprintln!("Error: {other}"),
+        }
+        process::exit(get_exit_code(&error));
+    }
+}
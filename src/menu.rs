@@ -0,0 +1,265 @@
+use inquire::{Confirm, InquireError, Password, PasswordDisplayMode, Select};
+use maker::{Assignment, AssignmentGroup, Course, Error, Result, SubmissionType};
+use maker::credentials::PASSPHRASE_VAR;
+use chrono::Utc;
+use std::{env, fmt};
+use std::sync::OnceLock;
+
+// asked for once, so a refreshed OAuth token is stored without asking again.
+static PASSPHRASE: OnceLock<String> = OnceLock::new();
+
+pub enum MenuOption {
+    NewLabNote,
+    SubmitLabNote,
+}
+
+impl fmt::Display for MenuOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MenuOption::NewLabNote => write!(f, "New Lab Note"),
+            MenuOption::SubmitLabNote => write!(f, "Submit Lab Note"),
+        }
+    }
+}
+
+impl MenuOption {
+    pub fn main_menu() -> Result<MenuOption> {
+        println!("--- MAIN MENU ---");
+
+        let main_options: Vec<MenuOption> = vec!{
+            MenuOption::NewLabNote,
+            MenuOption::SubmitLabNote,
+        };
+
+        let answer: MenuOption = Select::new("What would you like to do?", main_options)
+            .prompt()
+            .map_err(prompt_error)?;
+
+        Ok(answer)
+    }
+}
+
+pub fn prompt_error(error: InquireError) -> Error {
+    match error {
+        InquireError::OperationCanceled | InquireError::OperationInterrupted => Error::Cancelled,
+        InquireError::NotTTY => Error::Prompt(String::from("No terminal to prompt on, pass the missing value as a flag instead")),
+        other => Error::Prompt(other.to_string()),
+    }
+}
+
+// finds the item whose id or name matches the query, falling back to a unique partial name match.
+fn find_match<T>(mut items: Vec<T>, query: &str, kind: &str, get_id: fn(&T) -> i64, get_name: fn(&T) -> &str) -> Result<T> {
+    let query: &str = query.trim();
+    let lower_query: String = query.to_lowercase();
+
+    if let Ok(id) = query.parse::<i64>() {
+        if let Some(position) = items.iter().position(|x| get_id(x) == id) {
+            return Ok(items.swap_remove(position));
+        }
+    }
+    if let Some(position) = items.iter().position(|x| get_name(x).to_lowercase() == lower_query) {
+        return Ok(items.swap_remove(position));
+    }
+
+    let mut matches: Vec<T> = items.into_iter()
+        .filter(|x| get_name(x).to_lowercase().contains(&lower_query))
+        .collect();
+    match matches.len() {
+        0 => Err(Error::NotFound(format!("No {kind} matches `{query}`"))),
+        1 => Ok(matches.remove(0)),
+        _ => {
+            let names: Vec<&str> = matches.iter().map(get_name).collect();
+            Err(Error::NotFound(format!("`{query}` matches more than one {kind}: {}", names.join(", "))))
+        },
+    }
+}
+
+pub fn choose_course(courses: Vec<Course>, query: Option<&str>) -> Result<Course> {
+    if courses.is_empty() {
+        return Err(Error::NotFound(String::from("No courses found")));
+    }
+    if let Some(query) = query {
+        return find_match(courses, query, "course", |x| x.id, |x| &x.name);
+    }
+    Select::new("Which course would you like to select?", courses)
+        .prompt()
+        .map_err(prompt_error)
+}
+
+/// An assignment in the picker, with whether its lab note already exists.
+pub struct AssignmentChoice {
+    pub assignment: Assignment,
+    pub has_note: bool,
+    // the longest name in the picker, to line the columns up.
+    name_width: usize,
+}
+
+impl fmt::Display for AssignmentChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let assignment: &Assignment = &self.assignment;
+        let group: &str = match assignment.due_at {
+            Some(due_at) if due_at < Utc::now() => "overdue",
+            Some(_) => "upcoming",
+            None => "no date",
+        };
+        let due_date: String = assignment.get_local_due_date().unwrap_or_default();
+        let points: String = assignment.get_points().map(|x| format!("{x} pts")).unwrap_or_default();
+        let submitted: &str = if assignment.is_submitted() { "submitted" } else { "" };
+        let note: &str = if self.has_note { "note" } else { "" };
+        let line: String = format!(
+            "{group:<8} │ {:<width$}  {due_date:<16}  {points:>8}  {submitted:<9}  {note}",
+            assignment.name,
+            width = self.name_width,
+        );
+        write!(f, "{}", line.trim_end())
+    }
+}
+
+impl AssignmentChoice {
+    pub fn new(assignment: Assignment, has_note: bool) -> AssignmentChoice {
+        AssignmentChoice { assignment, has_note, name_width: 0 }
+    }
+}
+
+pub fn choose_assignment(mut choices: Vec<AssignmentChoice>, query: Option<&str>) -> Result<Assignment> {
+    if choices.is_empty() {
+        return Err(Error::NotFound(String::from("No assignments found in the lab group")));
+    }
+    if let Some(query) = query {
+        return find_match(choices, query, "assignment", |x| x.assignment.id, |x| &x.assignment.name)
+            .map(|x| x.assignment);
+    }
+
+    let name_width: usize = choices.iter().map(|x| x.assignment.name.chars().count()).max().unwrap_or(0);
+    for choice in choices.iter_mut() {
+        choice.name_width = name_width;
+    }
+    Select::new("Which assignment would you like to make a lab note for?", choices)
+        .prompt()
+        .map(|x| x.assignment)
+        .map_err(prompt_error)
+}
+
+/// An assignment group to pick assignments from, or all of them.
+pub enum GroupChoice {
+    Group(AssignmentGroup),
+    All,
+}
+
+impl fmt::Display for GroupChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GroupChoice::Group(group) => write!(f, "{group}"),
+            GroupChoice::All => write!(f, "All assignments"),
+        }
+    }
+}
+
+// asked when none of the course's groups match `assignment_groups`. `None` is every assignment.
+pub fn choose_assignment_group(groups: Vec<AssignmentGroup>) -> Result<Option<AssignmentGroup>> {
+    let mut options: Vec<GroupChoice> = groups.into_iter().map(GroupChoice::Group).collect();
+    options.push(GroupChoice::All);
+    let choice: GroupChoice = Select::new("No lab group found, which assignment group has the labs?", options)
+        .prompt()
+        .map_err(prompt_error)?;
+    match choice {
+        GroupChoice::Group(group) => Ok(Some(group)),
+        GroupChoice::All => Ok(None),
+    }
+}
+
+pub fn choose_submission_type(submission_types: Vec<SubmissionType>) -> Result<SubmissionType> {
+    if submission_types.len() == 1 {
+        return Ok(submission_types[0]);
+    }
+    Select::new("How would you like to submit it?", submission_types)
+        .prompt()
+        .map_err(prompt_error)
+}
+
+pub fn get_token_input() -> Result<String> {
+    Password::new("Canvas access token:")
+        .without_confirmation()
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .prompt()
+        .map_err(prompt_error)
+}
+
+// the passphrase for the encrypted credentials file, `$MAKER_PASSPHRASE` skips the prompt.
+pub fn get_passphrase() -> Result<String> {
+    if let Some(passphrase) = PASSPHRASE.get() {
+        return Ok(passphrase.clone());
+    }
+    let passphrase: String = match env::var(PASSPHRASE_VAR) {
+        Ok(passphrase) => passphrase,
+        Err(_) => Password::new("Passphrase for the credentials file:")
+            .without_confirmation()
+            .with_display_mode(PasswordDisplayMode::Masked)
+            .prompt()
+            .map_err(prompt_error)?,
+    };
+    Ok(PASSPHRASE.get_or_init(|| passphrase).clone())
+}
+
+pub fn create_passphrase() -> Result<String> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_VAR) {
+        return Ok(passphrase);
+    }
+    println!("No keyring available, the token will be stored in an encrypted file.");
+    Password::new("Passphrase for the credentials file:")
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .with_custom_confirmation_message("Passphrase again:")
+        .prompt()
+        .map_err(prompt_error)
+}
+
+pub fn should_create_dir(path: &str, yes: bool) -> bool {
+    if yes {
+        return true;
+    }
+    let ans = Confirm::new(&format!("{path} directory doesn't exist, do you want to create it?"))
+        .with_default(true)
+        .prompt();
+    matches!(ans, Ok(true))
+}
+
+pub enum ExistingFileAction {
+    Keep,
+    Merge,
+    Overwrite,
+}
+
+impl fmt::Display for ExistingFileAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExistingFileAction::Keep => write!(f, "Keep it as it is"),
+            ExistingFileAction::Merge => write!(f, "Merge the new questions in, keeping my answers"),
+            ExistingFileAction::Overwrite => write!(f, "Overwrite it"),
+        }
+    }
+}
+
+// with --yes, the new questions are merged in so no answers are lost. If we can't ask, it's kept.
+pub fn choose_existing_file_action(file_path: &str, yes: bool) -> ExistingFileAction {
+    if yes {
+        return ExistingFileAction::Merge;
+    }
+    let options: Vec<ExistingFileAction> = vec!{
+        ExistingFileAction::Keep,
+        ExistingFileAction::Merge,
+        ExistingFileAction::Overwrite,
+    };
+    Select::new(&format!("{file_path} already exists, what do you want to do?"), options)
+        .prompt()
+        .unwrap_or(ExistingFileAction::Keep)
+}
+
+pub fn should_submit(file_path: &str, assignment_name: &str, yes: bool) -> bool {
+    if yes {
+        return true;
+    }
+    let ans = Confirm::new(&format!("Submit {file_path} for {assignment_name}?"))
+        .with_default(true)
+        .prompt();
+    matches!(ans, Ok(true))
+}
@@ -0,0 +1,405 @@
+use std::collections::HashSet;
+use std::fmt;
+
+const QUESTIONS_HEADER: &str = "## Questions";
+const MARKER_START: &str = "<!-- maker:";
+const REMOVED_MARKER: &str = "<!-- maker: removed question -->";
+
+// how alike two questions have to be to be treated as the same question.
+const SAME_NUMBER_OVERLAP: f64 = 0.6;
+const ANY_NUMBER_SIMILARITY: f64 = 0.7;
+
+/// A question from a lab note's `## Questions` section, with whatever was written under it.
+pub struct NoteQuestion {
+    /// The question's number, `None` for questions that were removed by an earlier merge.
+    pub number: Option<u32>,
+    /// The question, including its sub-list, without the list marker.
+    pub text: String,
+    /// Everything written under the question.
+    pub answer: String,
+}
+
+/// What happened to the questions while merging.
+#[derive(Default)]
+pub struct MergeReport {
+    /// Answers carried over to the new questions.
+    pub kept: usize,
+    /// Questions that weren't in the old note.
+    pub added: usize,
+    /// Questions whose text changed, flagged with a marker comment.
+    pub changed: usize,
+    /// Questions that are gone from the assignment, kept at the bottom with a marker comment.
+    pub removed: usize,
+}
+
+impl fmt::Display for MergeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "kept {} answers, added {} questions, flagged {} changed and {} removed questions",
+            self.kept, self.added, self.changed, self.removed,
+        )
+    }
+}
+
+// the note, split around its questions section.
+struct NoteSections<'a> {
+    before: &'a str,
+    questions: &'a str,
+    after: &'a str,
+}
+
+impl<'a> NoteSections<'a> {
+    fn split(note: &'a str) -> Option<NoteSections<'a>> {
+        let start: usize = find_line(note, 0, |x| x.trim_end() == QUESTIONS_HEADER)?;
+        let body_start: usize = note[start..].find('\n').map(|x| start + x + 1).unwrap_or(note.len());
+        // the section ends at the next heading at the same level or above.
+        let end: usize = find_line(note, body_start, |x| x.starts_with("# ") || x.starts_with("## "))
+            .unwrap_or(note.len());
+        Some(NoteSections {
+            before: &note[..start],
+            questions: &note[body_start..end],
+            after: &note[end..],
+        })
+    }
+}
+
+// finds the byte offset of the first line at or after `from` matching `is_match`.
+fn find_line(text: &str, from: usize, is_match: impl Fn(&str) -> bool) -> Option<usize> {
+    let mut offset: usize = from;
+    for line in text[from..].split_inclusive('\n') {
+        if is_match(line) {
+            return Some(offset);
+        }
+        offset += line.len();
+    }
+    None
+}
+
+// `3. question` gives the number and the question.
+fn get_numbered_item(line: &str) -> Option<(u32, &str)> {
+    let (number, text) = line.split_once(". ")?;
+    let number: u32 = number.parse().ok()?;
+    Some((number, text))
+}
+
+// a question goes on over its indented sub-list items, and the line after a trailing `\` line break.
+// Any other line, even an indented one, is the answer.
+fn is_question_text_line(previous: &str, line: &str) -> bool {
+    if !line.starts_with(char::is_whitespace) || line.trim().is_empty() {
+        return false;
+    }
+    let item: &str = line.trim_start();
+    previous.ends_with('\\') || item.starts_with("- ") || get_numbered_item(item).is_some()
+}
+
+/// Parses the questions, and the answers under them, out of a lab note.
+pub fn parse_questions(note: &str) -> Vec<NoteQuestion> {
+    let Some(sections) = NoteSections::split(note) else {
+        return Vec::new();
+    };
+
+    let mut questions: Vec<NoteQuestion> = Vec::new();
+    let mut in_question_text: bool = false;
+    let mut after_removed_marker: bool = false;
+    let mut after_blank_line: bool = true;
+    let mut next_number: Option<u32> = None;
+    let mut answer_lines: Vec<&str> = Vec::new();
+
+    for line in sections.questions.lines() {
+        // numbered lists in an answer don't count, questions are numbered in order and start a paragraph.
+        let question_start: Option<(Option<u32>, &str)> = if after_removed_marker {
+            line.strip_prefix("- ").map(|x| (None, x))
+        } else if after_blank_line {
+            get_numbered_item(line)
+                .filter(|(number, _)| next_number.is_none_or(|x| x == *number))
+                .map(|(number, text)| (Some(number), text))
+        } else {
+            None
+        };
+        after_removed_marker = line.trim_end() == REMOVED_MARKER;
+        after_blank_line = line.trim().is_empty() || after_removed_marker;
+
+        if let Some((number, text)) = question_start {
+            finish_answer(questions.last_mut(), &mut answer_lines);
+            questions.push(NoteQuestion { number, text: text.to_string(), answer: String::new() });
+            if let Some(number) = number {
+                next_number = Some(number + 1);
+            }
+            in_question_text = true;
+        } else if in_question_text && questions.last().is_some_and(|x| is_question_text_line(x.text.lines().last().unwrap_or_default(), line)) {
+            if let Some(question) = questions.last_mut() {
+                question.text.push('\n');
+                question.text.push_str(line);
+            }
+        } else {
+            in_question_text = false;
+            // old markers are rebuilt on every merge.
+            if !line.trim_start().starts_with(MARKER_START) {
+                answer_lines.push(line);
+            }
+        }
+    }
+    finish_answer(questions.last_mut(), &mut answer_lines);
+
+    questions
+}
+
+fn finish_answer(question: Option<&mut NoteQuestion>, answer_lines: &mut Vec<&str>) {
+    if let Some(question) = question {
+        question.answer = answer_lines.join("\n").trim_matches('\n').trim_end().to_string();
+    }
+    answer_lines.clear();
+}
+
+fn get_words(text: &str) -> HashSet<String> {
+    text.split(|x: char| !x.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_lowercase())
+        .collect()
+}
+
+// how many of all their words the questions share, from 0 to 1.
+fn get_similarity(a: &str, b: &str) -> f64 {
+    let a_words: HashSet<String> = get_words(a);
+    let b_words: HashSet<String> = get_words(b);
+    if a_words.is_empty() && b_words.is_empty() {
+        return 1.0;
+    }
+    let shared: usize = a_words.intersection(&b_words).count();
+    let total: usize = a_words.union(&b_words).count();
+    shared as f64 / total as f64
+}
+
+// how much of the shorter question is in the longer one, from 0 to 1.
+fn get_overlap(a: &str, b: &str) -> f64 {
+    let a_words: HashSet<String> = get_words(a);
+    let b_words: HashSet<String> = get_words(b);
+    let smallest: usize = a_words.len().min(b_words.len());
+    if smallest == 0 {
+        return 0.0;
+    }
+    a_words.intersection(&b_words).count() as f64 / smallest as f64
+}
+
+// the same words in the same order, ignoring case and spacing.
+fn is_same_text(a: &str, b: &str) -> bool {
+    let a_words = a.split_whitespace().map(|x| x.to_lowercase());
+    let b_words = b.split_whitespace().map(|x| x.to_lowercase());
+    a_words.eq(b_words)
+}
+
+// pairs each new question with the old question it came from, if any.
+fn match_questions(old: &[NoteQuestion], new: &[NoteQuestion]) -> Vec<Option<usize>> {
+    let mut matches: Vec<Option<usize>> = vec![None; new.len()];
+    let mut used: Vec<bool> = vec![false; old.len()];
+
+    // same text, wherever it moved to.
+    for (i, question) in new.iter().enumerate() {
+        if let Some(j) = (0..old.len()).find(|j| !used[*j] && is_same_text(&old[*j].text, &question.text)) {
+            matches[i] = Some(j);
+            used[j] = true;
+        }
+    }
+    // same number and close enough.
+    for (i, question) in new.iter().enumerate() {
+        if matches[i].is_some() || question.number.is_none() {
+            continue;
+        }
+        if let Some(j) = (0..old.len()).find(|j| {
+            !used[*j] && old[*j].number == question.number
+                && get_overlap(&old[*j].text, &question.text) >= SAME_NUMBER_OVERLAP
+        }) {
+            matches[i] = Some(j);
+            used[j] = true;
+        }
+    }
+    // anything else that is very close.
+    for (i, question) in new.iter().enumerate() {
+        if matches[i].is_some() {
+            continue;
+        }
+        let best: Option<(usize, f64)> = (0..old.len())
+            .filter(|j| !used[*j])
+            .map(|j| (j, get_similarity(&old[j].text, &question.text)))
+            .filter(|(_, similarity)| *similarity >= ANY_NUMBER_SIMILARITY)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((j, _)) = best {
+            matches[i] = Some(j);
+            used[j] = true;
+        }
+    }
+
+    matches
+}
+
+// comments can't hold `--`, so keep the old question readable without it.
+fn get_marker_text(text: &str) -> String {
+    text.lines().next().unwrap_or("").replace("--", "- -")
+}
+
+fn render_question(section: &mut String, marker: &str, text: &str, comment: Option<String>, answer: &str) {
+    section.push_str(&format!("\n{marker} {text}\n"));
+    if let Some(comment) = comment {
+        section.push_str(&format!("{comment}\n"));
+    }
+    if !answer.is_empty() {
+        section.push_str(&format!("\n{answer}\n"));
+    }
+    section.push('\n');
+}
+
+/// Merges a freshly generated lab note into an existing one.
+///
+/// The new note's questions are matched to the old ones by number and by how
+/// similar their text is. Answers are kept under their question, new questions
+/// are added, changed questions get a marker comment, and removed questions
+/// are kept (with their answers) at the bottom of the section.
+/// Anything after the old `## Questions` section is kept as it was.
+pub fn merge_notes(existing: &str, generated: &str) -> (String, MergeReport) {
+    let mut report: MergeReport = MergeReport::default();
+    let old_questions: Vec<NoteQuestion> = parse_questions(existing);
+    let new_questions: Vec<NoteQuestion> = parse_questions(generated);
+
+    // without both sections there's nowhere to put the answers, so don't touch the old note.
+    let (Some(old_sections), Some(new_sections)) = (NoteSections::split(existing), NoteSections::split(generated)) else {
+        return (existing.to_string(), report);
+    };
+    let matches: Vec<Option<usize>> = match_questions(&old_questions, &new_questions);
+
+    let mut section: String = format!("{QUESTIONS_HEADER}\n");
+    for (question, matched) in new_questions.iter().zip(matches.iter()) {
+        let marker: String = match question.number {
+            Some(number) => format!("{number}."),
+            None => String::from("-"),
+        };
+        match matched.map(|j| &old_questions[j]) {
+            Some(old) => {
+                let comment: Option<String> = if is_same_text(&old.text, &question.text) {
+                    None
+                } else {
+                    report.changed += 1;
+                    Some(format!("<!-- maker: question changed, it used to be: {} -->", get_marker_text(&old.text)))
+                };
+                if !old.answer.is_empty() {
+                    report.kept += 1;
+                }
+                render_question(&mut section, &marker, &question.text, comment, &old.answer);
+            },
+            None => {
+                report.added += 1;
+                render_question(&mut section, &marker, &question.text, None, "");
+            },
+        }
+    }
+
+    for (j, old) in old_questions.iter().enumerate() {
+        if matches.contains(&Some(j)) {
+            continue;
+        }
+        report.removed += 1;
+        section.push_str(&format!("\n{REMOVED_MARKER}"));
+        render_question(&mut section, "-", &old.text, None, &old.answer);
+    }
+
+    // keep whatever the user wrote after the questions.
+    let after: &str = if old_sections.after.trim().is_empty() {
+        new_sections.after
+    } else {
+        old_sections.after
+    };
+
+    (format!("{}{section}{after}", new_sections.before), report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(questions: &str) -> String {
+        format!("# Lab 1\n\n## Questions\n{questions}")
+    }
+
+    #[test]
+    fn moved_question_keeps_its_answer() {
+        let existing: String = note("\n1. What is a stack?\n\nLIFO.\n\n2. What is a queue?\n\nFIFO.\n\n");
+        let generated: String = note("\n1. What is a queue?\n\n2. What is a stack?\n\n");
+        let (merged, report) = merge_notes(&existing, &generated);
+
+        assert_eq!(merged, note("\n1. What is a queue?\n\nFIFO.\n\n\n2. What is a stack?\n\nLIFO.\n\n"));
+        assert_eq!((report.kept, report.added, report.changed, report.removed), (2, 0, 0, 0));
+    }
+
+    #[test]
+    fn reworded_question_is_flagged() {
+        let existing: String = note("\n1. Explain how the stack grows.\n\nDownwards.\n\n");
+        let generated: String = note("\n1. Explain how the stack grows on x86.\n\n");
+        let (merged, report) = merge_notes(&existing, &generated);
+
+        assert_eq!(merged, note(concat!(
+            "\n1. Explain how the stack grows on x86.\n",
+            "<!-- maker: question changed, it used to be: Explain how the stack grows. -->\n",
+            "\nDownwards.\n\n",
+        )));
+        assert_eq!((report.kept, report.changed), (1, 1));
+    }
+
+    #[test]
+    fn removed_question_stays_at_the_bottom() {
+        let existing: String = note("\n1. What is a stack?\n\nLIFO.\n\n2. What is a queue?\n\nFIFO.\n\n");
+        let generated: String = note("\n1. What is a stack?\n\n");
+        let (merged, report) = merge_notes(&existing, &generated);
+
+        assert_eq!(merged, note(&format!("\n1. What is a stack?\n\nLIFO.\n\n\n{REMOVED_MARKER}\n- What is a queue?\n\nFIFO.\n\n")));
+        assert_eq!((report.kept, report.removed), (1, 1));
+
+        // merging again doesn't move, duplicate or lose it.
+        let (merged_again, report) = merge_notes(&merged, &generated);
+        assert_eq!(merged_again, merged);
+        assert_eq!((report.kept, report.removed), (1, 1));
+    }
+
+    #[test]
+    fn numbered_list_in_an_answer_isnt_a_question() {
+        let existing: String = note("\n1. How do you build it?\n\nSteps:\n\n1. configure\n2. make\n\n2. Does it run?\n\nYes.\n");
+        let questions: Vec<NoteQuestion> = parse_questions(&existing);
+
+        assert_eq!(questions.len(), 2);
+        assert_eq!(questions[0].answer, "Steps:\n\n1. configure\n2. make");
+        assert_eq!(questions[1].text, "Does it run?");
+        assert_eq!(questions[1].answer, "Yes.");
+    }
+
+    #[test]
+    fn line_break_in_a_question_isnt_an_answer() {
+        let generated: String = note("\n1. Line\\\n    break\n    - Sub\n\n2. Next\n\n");
+        let questions: Vec<NoteQuestion> = parse_questions(&generated);
+
+        assert_eq!(questions.len(), 2);
+        assert_eq!(questions[0].text, "Line\\\n    break\n    - Sub");
+        assert!(questions.iter().all(|x| x.answer.is_empty()));
+    }
+
+    #[test]
+    fn indented_answer_right_under_a_question_is_kept() {
+        let existing: String = note("\n1. What is a stack?\n    my answer\n\n2. What is a queue?\n\n");
+        let generated: String = note("\n1. What is a stack?\n\n2. What is a queue?\n\n");
+        let questions: Vec<NoteQuestion> = parse_questions(&existing);
+        assert_eq!(questions[0].text, "What is a stack?");
+        assert_eq!(questions[0].answer.trim(), "my answer");
+
+        let (merged, report) = merge_notes(&existing, &generated);
+        assert!(merged.contains("1. What is a stack?\n\n    my answer\n"));
+        assert!(!merged.contains(MARKER_START));
+        assert_eq!(report.changed, 0);
+    }
+
+    #[test]
+    fn content_after_questions_is_kept() {
+        let existing: String = note("\n1. What is a stack?\n\nLIFO.\n\n## Reflection\n\nIt went well.\n");
+        let generated: String = note("\n1. What is a stack?\n\n## Reflection\n\n");
+        let (merged, _) = merge_notes(&existing, &generated);
+
+        assert!(merged.ends_with("## Reflection\n\nIt went well.\n"));
+        assert!(merged.contains("1. What is a stack?\n\nLIFO.\n"));
+    }
+}
@@ -0,0 +1,241 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::requester::{Assignment, Course};
+
+/// Where lab notes go when `naming_pattern` isn't set, relative to `base_dir`.
+pub const DEFAULT_NAMING_PATTERN: &str = "{course_short}/lab/{assignment_slug}.md";
+
+/// The variables a naming pattern can use.
+pub const NAMING_VARIABLES: [&str; 9] = [
+    "term", "course_code", "course_name", "course_short", "course_id",
+    "course_slug", "assignment_name", "assignment_slug", "assignment_id",
+];
+
+// characters that aren't allowed in a file name on some system.
+const ILLEGAL_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+// how many characters of the course name `{course_short}` keeps, `COS 120 - Intro` is `cos120`.
+const COURSE_SHORT_LENGTH: usize = 7;
+
+enum Piece {
+    Literal(String),
+    Variable(String),
+}
+
+/// A pattern for lab note paths, like `{term}/{course_code}/lab/{assignment_slug}.md`.
+///
+/// Variables are replaced with the course's and assignment's values, with the
+/// characters that aren't allowed in file names stripped. `/` in the pattern
+/// separates directories, and empty directories (like a course without a term) are skipped.
+pub struct NamingPattern {
+    pieces: Vec<Piece>,
+}
+
+impl Default for NamingPattern {
+    fn default() -> NamingPattern {
+        NamingPattern::new(DEFAULT_NAMING_PATTERN).expect("the default naming pattern is valid")
+    }
+}
+
+impl NamingPattern {
+    /// Parses a pattern, checking that every variable is one of [`NAMING_VARIABLES`].
+    pub fn new(pattern: &str) -> Result<NamingPattern> {
+        let invalid = |reason: String| Error::Config(format!("Invalid `naming_pattern` `{pattern}`: {reason}"));
+
+        if pattern.trim().is_empty() {
+            return Err(invalid(String::from("it is empty")));
+        }
+        if Path::new(pattern).is_absolute() || pattern.split('/').any(|x| x == "..") {
+            return Err(invalid(String::from("it must stay inside `base_dir`")));
+        }
+
+        let mut pieces: Vec<Piece> = Vec::new();
+        let mut rest: &str = pattern;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                pieces.push(Piece::Literal(rest[..start].to_string()));
+            }
+            let end: usize = rest[start..].find('}')
+                .map(|x| start + x)
+                .ok_or_else(|| invalid(String::from("a `{` is never closed")))?;
+            let variable: &str = rest[start + 1..end].trim();
+            if !NAMING_VARIABLES.contains(&variable) {
+                return Err(invalid(format!("unknown variable `{{{variable}}}`, it must be one of {}", NAMING_VARIABLES.join(", "))));
+            }
+            pieces.push(Piece::Variable(variable.to_string()));
+            rest = &rest[end + 1..];
+        }
+        if rest.contains('}') {
+            return Err(invalid(String::from("a `}` is never opened")));
+        }
+        if !rest.is_empty() {
+            pieces.push(Piece::Literal(rest.to_string()));
+        }
+
+        Ok(NamingPattern { pieces })
+    }
+
+    /// The lab note path for an assignment, relative to `base_dir`.
+    ///
+    /// `assignments` are the other assignments in the course. If one with a
+    /// lower id gets the same path, the assignment id is added to the file name,
+    /// so the same assignment always gets the same path.
+    pub fn get_path(&self, course: &Course, assignment: &Assignment, assignments: &[Assignment]) -> PathBuf {
+        let path: PathBuf = self.render(course, assignment);
+        let same_path: String = path.to_string_lossy().to_lowercase();
+        let is_taken: bool = assignments.iter()
+            .filter(|x| x.id < assignment.id)
+            .any(|x| self.render(course, x).to_string_lossy().to_lowercase() == same_path);
+        if !is_taken {
+            return path;
+        }
+
+        let stem: String = path.file_stem().map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+        let file_name: String = match path.extension() {
+            Some(extension) => format!("{stem}_{}.{}", assignment.id, extension.to_string_lossy()),
+            None => format!("{stem}_{}", assignment.id),
+        };
+        path.with_file_name(file_name)
+    }
+
+    fn render(&self, course: &Course, assignment: &Assignment) -> PathBuf {
+        let mut rendered: String = String::new();
+        for piece in self.pieces.iter() {
+            match piece {
+                Piece::Literal(text) => rendered.push_str(text),
+                Piece::Variable(name) => rendered.push_str(&clean_file_name(&get_variable(name, course, assignment))),
+            }
+        }
+
+        // literal `/` separate directories, skip the empty ones left by empty variables.
+        let mut path: PathBuf = PathBuf::new();
+        for segment in rendered.split('/') {
+            let segment: &str = segment.trim().trim_end_matches('.');
+            let is_normal: bool = matches!(Path::new(segment).components().next(), Some(Component::Normal(_)));
+            if !segment.is_empty() && is_normal {
+                path.push(segment);
+            }
+        }
+        if path.as_os_str().is_empty() {
+            path.push(assignment.id.to_string());
+        }
+        path
+    }
+}
+
+fn get_variable(name: &str, course: &Course, assignment: &Assignment) -> String {
+    match name {
+        "term" => course.term.as_ref().map(|x| x.name.clone()).unwrap_or_default(),
+        "course_code" => course.course_code.clone(),
+        "course_name" => course.name.clone(),
+        "course_short" => get_course_short(&course.name),
+        "course_id" => course.id.to_string(),
+        "course_slug" => get_slug(&course.name),
+        "assignment_name" => assignment.name.clone(),
+        "assignment_slug" => {
+            let slug: String = get_slug(&assignment.name);
+            // nothing left of the name, the id is still unique.
+            if slug.is_empty() { assignment.id.to_string() } else { slug }
+        },
+        "assignment_id" => assignment.id.to_string(),
+        _ => String::new(),
+    }
+}
+
+// the start of the course name, lowercase without spaces.
+fn get_course_short(name: &str) -> String {
+    name.chars()
+        .take(COURSE_SHORT_LENGTH)
+        .filter(|x| !x.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Lowercases `text` and replaces everything but letters, digits, `-` and `.` with
+/// `_`, for any script. `Lab 2: Ünïcode Arrays` becomes `lab_2_ünïcode_arrays`.
+pub fn get_slug(text: &str) -> String {
+    let mut slug: String = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || c == '-' || c == '.' {
+            slug.push(c);
+        } else if !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    // no hidden files, and no dangling separators.
+    slug.trim_matches(|x| x == '_' || x == '-' || x == '.').to_string()
+}
+
+/// Strips the characters that aren't allowed in file names, like `/` and `:`.
+pub fn clean_file_name(text: &str) -> String {
+    text.chars()
+        .filter(|x| !ILLEGAL_CHARS.contains(x) && !x.is_control())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn get_course(name: &str, term: Option<&str>) -> Course {
+        let term = term.map(|x| json!({"id": 1, "name": x}));
+        serde_json::from_value(json!({"id": 10, "name": name, "course_code": "COS 120", "term": term})).unwrap()
+    }
+
+    fn get_assignment(id: i64, name: &str) -> Assignment {
+        serde_json::from_value(json!({"id": id, "name": name})).unwrap()
+    }
+
+    #[test]
+    fn course_short_handles_short_and_multibyte_names() {
+        assert_eq!(get_course_short("COS 120 - Intro"), "cos120");
+        assert_eq!(get_course_short("AI"), "ai");
+        assert_eq!(get_course_short("Ünïcödé Course"), "ünïcödé");
+        assert_eq!(get_course_short("日本語の授業です"), "日本語の授業で");
+    }
+
+    #[test]
+    fn slug_keeps_any_script_and_drops_separators() {
+        assert_eq!(get_slug("Lab 2: Ünïcode Arrays"), "lab_2_ünïcode_arrays");
+        assert_eq!(get_slug("Input/Output: Files"), "input_output_files");
+        assert_eq!(get_slug("..hidden__"), "hidden");
+        assert_eq!(get_slug("???"), "");
+    }
+
+    #[test]
+    fn empty_segments_are_skipped() {
+        let pattern: NamingPattern = NamingPattern::new("{term}/{course_code}/{assignment_slug}.md").unwrap();
+        let assignment: Assignment = get_assignment(1, "Lab 1");
+        let path: PathBuf = pattern.get_path(&get_course("Intro", None), &assignment, &[]);
+        assert_eq!(path, PathBuf::from("COS 120/lab_1.md"));
+        let path: PathBuf = pattern.get_path(&get_course("Intro", Some("Fall 2026")), &assignment, &[]);
+        assert_eq!(path, PathBuf::from("Fall 2026/COS 120/lab_1.md"));
+    }
+
+    #[test]
+    fn patterns_must_stay_in_base_dir() {
+        assert!(NamingPattern::new("../{assignment_slug}.md").is_err());
+        assert!(NamingPattern::new("notes/../../{assignment_slug}.md").is_err());
+        assert!(NamingPattern::new("/tmp/{assignment_slug}.md").is_err());
+        assert!(NamingPattern::new("{unknown}.md").is_err());
+        assert!(NamingPattern::new("{assignment_slug.md").is_err());
+    }
+
+    #[test]
+    fn collisions_get_the_id_added() {
+        let pattern: NamingPattern = NamingPattern::default();
+        let course: Course = get_course("COS 120 - Intro", None);
+        let assignments: Vec<Assignment> = vec!{get_assignment(7, "Lab 1"), get_assignment(3, "lab 1"), get_assignment(5, "Lab 2")};
+
+        // the lowest id keeps the plain name, whatever order they're listed in.
+        assert_eq!(pattern.get_path(&course, &assignments[1], &assignments), PathBuf::from("cos120/lab/lab_1.md"));
+        assert_eq!(pattern.get_path(&course, &assignments[0], &assignments), PathBuf::from("cos120/lab/lab_1_7.md"));
+        assert_eq!(pattern.get_path(&course, &assignments[2], &assignments), PathBuf::from("cos120/lab/lab_2.md"));
+        let reversed: Vec<Assignment> = assignments.iter().rev().cloned().collect();
+        assert_eq!(pattern.get_path(&course, &assignments[0], &reversed), PathBuf::from("cos120/lab/lab_1_7.md"));
+    }
+}
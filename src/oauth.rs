@@ -0,0 +1,377 @@
+use chrono::Utc;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+use crate::credentials;
+use crate::error::{Error, Result};
+use crate::requester::CanvasClient;
+
+/// The port the login listens on for Canvas' redirect, when `redirect_port` isn't set.
+pub const DEFAULT_REDIRECT_PORT: u16 = 8749;
+
+/// How long the login waits for the user to finish in the browser.
+pub const LOGIN_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+const CALLBACK_PATH: &str = "/oauth/callback";
+
+fn default_redirect_port() -> u16 {
+    DEFAULT_REDIRECT_PORT
+}
+
+/// A Canvas developer key, for logging in with OAuth2 instead of a personal access token.
+#[derive(Deserialize, Clone)]
+pub struct OAuthConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Defaults to `<canvas_url>/login/oauth2/auth`.
+    pub authorize_url: Option<String>,
+    /// Defaults to `<canvas_url>/login/oauth2/token`.
+    pub token_url: Option<String>,
+    /// The developer key's redirect uri has to be `http://127.0.0.1:<port>/oauth/callback`.
+    #[serde(default = "default_redirect_port")]
+    pub redirect_port: u16,
+}
+
+impl OAuthConfig {
+    fn get_authorize_url(&self, canvas_url: &str) -> String {
+        self.authorize_url.clone().unwrap_or_else(|| format!("{canvas_url}/login/oauth2/auth"))
+    }
+
+    fn get_token_url(&self, canvas_url: &str) -> String {
+        self.token_url.clone().unwrap_or_else(|| format!("{canvas_url}/login/oauth2/token"))
+    }
+
+    fn get_redirect_uri(&self) -> String {
+        format!("http://127.0.0.1:{}{CALLBACK_PATH}", self.redirect_port)
+    }
+}
+
+/// The tokens from an OAuth2 login, stored as JSON in place of a personal access token.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OAuthTokens {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// When the access token expires, in seconds since the epoch.
+    pub expires_at: Option<i64>,
+}
+
+impl OAuthTokens {
+    /// Reads stored tokens, `None` if what's stored is a plain personal access token.
+    pub fn from_stored(stored: &str) -> Option<OAuthTokens> {
+        serde_json::from_str(stored).ok()
+    }
+
+    /// The tokens as they are stored.
+    pub fn to_stored(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|error| Error::json("the OAuth tokens", error))
+    }
+}
+
+// what Canvas answers a token request with, a refresh doesn't include a new refresh token.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+}
+
+impl TokenResponse {
+    fn into_tokens(self, old_refresh_token: Option<String>) -> OAuthTokens {
+        OAuthTokens {
+            access_token: self.access_token,
+            refresh_token: self.refresh_token.or(old_refresh_token),
+            expires_at: self.expires_in.map(|x| Utc::now().timestamp() + x),
+        }
+    }
+}
+
+async fn request_tokens(client: &Client, token_url: &str, form: &[(&str, &str)], timeout: Duration) -> Result<TokenResponse> {
+    let response = client.post(token_url).form(form).timeout(timeout).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body: String = response.text().await.unwrap_or_default();
+        return Err(Error::Status { status, url: token_url.to_string(), body });
+    }
+    response.json::<TokenResponse>().await.map_err(Error::from)
+}
+
+fn get_state() -> String {
+    let mut bytes: [u8; 16] = [0; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+// waits for Canvas to send the browser back with the authorization code, giving up after `timeout`.
+async fn wait_for_code(listener: TcpListener, state: &str, timeout: Duration) -> Result<String> {
+    tokio::time::timeout(timeout, accept_code(listener, state)).await.unwrap_or_else(|_| {
+        Err(Error::Credential(String::from("The login wasn't finished in time, run `maker auth login --oauth` again")))
+    })
+}
+
+async fn accept_code(listener: TcpListener, state: &str) -> Result<String> {
+    loop {
+        let (stream, _) = listener.accept().await.map_err(|error| Error::io("the OAuth redirect listener", error))?;
+        let mut reader: BufReader<_> = BufReader::new(stream);
+        let mut request_line: String = String::new();
+        reader.read_line(&mut request_line).await.map_err(|error| Error::io("the OAuth redirect", error))?;
+
+        // `GET /oauth/callback?code=...&state=... HTTP/1.1`, anything else (like a favicon) is ignored.
+        let Some(target) = request_line.split_whitespace().nth(1) else {
+            continue;
+        };
+        let Ok(url) = Url::parse(&format!("http://127.0.0.1{target}")) else {
+            continue;
+        };
+        if url.path() != CALLBACK_PATH {
+            continue;
+        }
+        let get_param = |key: &str| url.query_pairs().find(|(x, _)| x == key).map(|(_, value)| value.to_string());
+
+        let result: Result<String> = if let Some(error) = get_param("error") {
+            Err(Error::Credential(format!("Canvas refused the login: {error}")))
+        } else if get_param("state").as_deref() != Some(state) {
+            Err(Error::Credential(String::from("The OAuth redirect didn't come from this login")))
+        } else {
+            get_param("code").ok_or_else(|| Error::Credential(String::from("The OAuth redirect didn't have a code")))
+        };
+
+        let message: &str = if result.is_ok() { "Logged in, you can close this tab." } else { "Login failed, see the terminal." };
+        let response: String = format!("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{message}", message.len());
+        // the browser not getting the page doesn't matter.
+        let _ = reader.get_mut().write_all(response.as_bytes()).await;
+        return result;
+    }
+}
+
+/// Runs the authorization-code flow: listens on localhost, hands the url the
+/// user has to open to `open_browser`, and trades the code for tokens.
+/// Gives up if the browser isn't sent back within [`LOGIN_TIMEOUT`], and
+/// the token request can take up to `timeout`.
+pub async fn login(canvas_url: &str, config: &OAuthConfig, timeout: Duration, open_browser: impl FnOnce(&str)) -> Result<OAuthTokens> {
+    let listener: TcpListener = TcpListener::bind(("127.0.0.1", config.redirect_port)).await
+        .map_err(|error| Error::io(format!("127.0.0.1:{}", config.redirect_port), error))?;
+    let state: String = get_state();
+    let redirect_uri: String = config.get_redirect_uri();
+
+    let authorize_url: String = config.get_authorize_url(canvas_url);
+    let authorize_url: Url = Url::parse_with_params(&authorize_url, &[
+        ("client_id", config.client_id.as_str()),
+        ("response_type", "code"),
+        ("redirect_uri", redirect_uri.as_str()),
+        ("state", state.as_str()),
+    ]).map_err(|error| Error::Config(format!("Invalid OAuth `authorize_url` `{authorize_url}`: {error}")))?;
+    open_browser(authorize_url.as_str());
+
+    let code: String = wait_for_code(listener, &state, LOGIN_TIMEOUT).await?;
+    let response: TokenResponse = request_tokens(&Client::new(), &config.get_token_url(canvas_url), &[
+        ("grant_type", "authorization_code"),
+        ("client_id", &config.client_id),
+        ("client_secret", &config.client_secret),
+        ("redirect_uri", &redirect_uri),
+        ("code", &code),
+    ], timeout).await?;
+    Ok(response.into_tokens(None))
+}
+
+/// A logged-in OAuth2 session, which gets a new access token when the old one
+/// expires and stores it back where it came from.
+pub struct OAuthSession {
+    client: Client,
+    canvas_url: String,
+    config: OAuthConfig,
+    tokens: Mutex<OAuthTokens>,
+    timeout: Duration,
+    // `None` keeps refreshed tokens in memory only.
+    credential_name: Option<String>,
+    file_only: bool,
+    get_passphrase: fn() -> Result<String>,
+}
+
+impl OAuthSession {
+    /// `credential_name` and `file_only` say where the tokens are stored, `get_passphrase` is
+    /// only called if they're in the encrypted file.
+    pub fn new(canvas_url: &str, config: OAuthConfig, tokens: OAuthTokens, credential_name: &str, file_only: bool, get_passphrase: fn() -> Result<String>) -> OAuthSession {
+        OAuthSession {
+            client: Client::new(),
+            canvas_url: canvas_url.to_string(),
+            config,
+            tokens: Mutex::new(tokens),
+            timeout: CanvasClient::DEFAULT_TIMEOUT,
+            credential_name: Some(credential_name.to_string()),
+            file_only,
+            get_passphrase,
+        }
+    }
+
+    /// Sets how long a token request can take.
+    pub fn timeout(mut self, timeout: Duration) -> OAuthSession {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Trades the refresh token for a new access token, stores both, and returns the access token.
+    /// If another request already refreshed past `expired_token`, that token is returned instead.
+    pub async fn refresh(&self, expired_token: &str) -> Result<String> {
+        let mut tokens = self.tokens.lock().await;
+        if tokens.access_token != expired_token {
+            return Ok(tokens.access_token.clone());
+        }
+        let refresh_token: String = tokens.refresh_token.clone()
+            .ok_or_else(|| Error::Credential(String::from("The access token expired and there's no refresh token, run `maker auth login --oauth` again")))?;
+
+        let response: TokenResponse = request_tokens(&self.client, &self.config.get_token_url(&self.canvas_url), &[
+            ("grant_type", "refresh_token"),
+            ("client_id", &self.config.client_id),
+            ("client_secret", &self.config.client_secret),
+            ("refresh_token", &refresh_token),
+        ], self.timeout).await?;
+        *tokens = response.into_tokens(Some(refresh_token));
+
+        if let Some(name) = &self.credential_name {
+            credentials::save_token(name, &tokens.to_stored()?, self.file_only, self.get_passphrase).await?;
+        }
+        Ok(tokens.access_token.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::requester::User;
+    use crate::test_server::{StubResponse, StubServer};
+
+    const TOKEN_PATH: &str = "/login/oauth2/token";
+
+    fn get_config(server: &StubServer, redirect_port: u16) -> OAuthConfig {
+        OAuthConfig {
+            client_id: String::from("id"),
+            client_secret: String::from("secret"),
+            authorize_url: Some(format!("{}/login/oauth2/auth", server.url)),
+            token_url: Some(format!("{}{TOKEN_PATH}", server.url)),
+            redirect_port,
+        }
+    }
+
+    // a session that doesn't store the refreshed tokens anywhere.
+    fn get_session(server: &StubServer, tokens: OAuthTokens) -> OAuthSession {
+        OAuthSession {
+            client: Client::new(),
+            canvas_url: server.url.clone(),
+            config: get_config(server, DEFAULT_REDIRECT_PORT),
+            tokens: Mutex::new(tokens),
+            timeout: CanvasClient::DEFAULT_TIMEOUT,
+            credential_name: None,
+            file_only: true,
+            get_passphrase: || Ok(String::new()),
+        }
+    }
+
+    fn get_expired_tokens() -> OAuthTokens {
+        OAuthTokens { access_token: String::from("old"), refresh_token: Some(String::from("refresh")), expires_at: None }
+    }
+
+    // a token endpoint that hands out `new`, and a canvas that only takes `new`.
+    async fn start_canvas() -> StubServer {
+        StubServer::start(|request| match request.path() {
+            TOKEN_PATH => StubResponse::new(200, r#"{"access_token": "new", "expires_in": 3600}"#),
+            _ if request.header("authorization") == Some("Bearer new") => StubResponse::new(200, r#"{"id": 1, "name": "Tester"}"#),
+            _ => StubResponse::new(401, r#"{"errors": [{"message": "Invalid access token."}]}"#),
+        }).await
+    }
+
+    async fn bind_listener() -> (TcpListener, u16) {
+        let listener: TcpListener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port: u16 = listener.local_addr().unwrap().port();
+        (listener, port)
+    }
+
+    #[tokio::test]
+    async fn login_trades_the_code_for_tokens() {
+        let server: StubServer = StubServer::start(|_| {
+            StubResponse::new(200, r#"{"access_token": "access", "refresh_token": "refresh", "expires_in": 3600}"#)
+        }).await;
+        // a free port for the redirect listener.
+        let port: u16 = bind_listener().await.1;
+
+        // the "browser" follows the redirect straight back with a code.
+        let tokens: OAuthTokens = login(&server.url, &get_config(&server, port), CanvasClient::DEFAULT_TIMEOUT, |url| {
+            let url: Url = Url::parse(url).unwrap();
+            let get_param = |key: &str| url.query_pairs().find(|(x, _)| x == key).map(|(_, value)| value.to_string()).unwrap();
+            let redirect: String = format!("{}?code=the-code&state={}", get_param("redirect_uri"), get_param("state"));
+            tokio::spawn(async move { reqwest::get(redirect).await });
+        }).await.unwrap();
+
+        assert_eq!(tokens.access_token, "access");
+        assert_eq!(tokens.refresh_token.as_deref(), Some("refresh"));
+        assert!(tokens.expires_at.is_some());
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+        assert!(requests[0].body.contains("grant_type=authorization_code"));
+        assert!(requests[0].body.contains("code=the-code"));
+    }
+
+    #[tokio::test]
+    async fn redirect_with_another_state_is_refused() {
+        let (listener, port) = bind_listener().await;
+        tokio::spawn(reqwest::get(format!("http://127.0.0.1:{port}{CALLBACK_PATH}?code=the-code&state=other")));
+
+        let result: Result<String> = wait_for_code(listener, "expected", Duration::from_secs(5)).await;
+        assert!(matches!(result, Err(Error::Credential(message)) if message.contains("didn't come from this login")));
+    }
+
+    #[tokio::test]
+    async fn abandoned_login_times_out() {
+        let (listener, _) = bind_listener().await;
+        let result: Result<String> = wait_for_code(listener, "expected", Duration::from_millis(50)).await;
+        assert!(matches!(result, Err(Error::Credential(message)) if message.contains("in time")));
+    }
+
+    #[tokio::test]
+    async fn expired_token_is_refreshed_on_401() {
+        let server: StubServer = start_canvas().await;
+        let client: CanvasClient = CanvasClient::new(&server.url, "old").unwrap()
+            .with_oauth(get_session(&server, get_expired_tokens()));
+
+        let user: User = User::get_self(&client).await.unwrap();
+        assert_eq!(user.name, "Tester");
+        assert_eq!(server.count(TOKEN_PATH), 1);
+        let refresh = server.requests().into_iter().find(|x| x.path() == TOKEN_PATH).unwrap();
+        assert!(refresh.body.contains("grant_type=refresh_token"));
+        assert!(refresh.body.contains("refresh_token=refresh"));
+
+        // the new token is kept, so there's no second refresh.
+        User::get_self(&client).await.unwrap();
+        assert_eq!(server.count(TOKEN_PATH), 1);
+    }
+
+    #[tokio::test]
+    async fn concurrent_refreshes_only_refresh_once() {
+        let server: StubServer = start_canvas().await;
+        let session: OAuthSession = get_session(&server, get_expired_tokens());
+
+        let (first, second) = tokio::join!(session.refresh("old"), session.refresh("old"));
+        assert_eq!(first.unwrap(), "new");
+        assert_eq!(second.unwrap(), "new");
+        assert_eq!(server.count(TOKEN_PATH), 1);
+        // the refresh token is kept when canvas doesn't send a new one.
+        assert_eq!(session.tokens.lock().await.refresh_token.as_deref(), Some("refresh"));
+    }
+
+    #[tokio::test]
+    async fn refresh_without_a_refresh_token_fails() {
+        let server: StubServer = start_canvas().await;
+        let tokens: OAuthTokens = OAuthTokens { refresh_token: None, ..get_expired_tokens() };
+        let client: CanvasClient = CanvasClient::new(&server.url, "old").unwrap()
+            .with_oauth(get_session(&server, tokens));
+
+        assert!(matches!(User::get_self(&client).await, Err(Error::Credential(_))));
+        assert_eq!(server.count(TOKEN_PATH), 0);
+    }
+}
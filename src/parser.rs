@@ -1,9 +1,11 @@
 use html_parser::Dom;
+use regex::{Regex, RegexBuilder};
 use serde::Deserialize;
-use serde_json::Value;
-use std::process;
+use std::collections::HashMap;
+
+use crate::converter;
+use crate::error::{Error, Result};
 
-const H1_KEY: &str = "h1";
 const OL_KEY: &str = "ol";
 const UL_KEY: &str = "ul";
 const LI_KEY: &str = "li";
@@ -11,48 +13,148 @@ const P_KEY: &str = "p";
 const EM_KEY: &str = "em";
 const CODE_KEY: &str = "code";
 
+// a bold paragraph used as a header ranks below every heading.
+const BOLD_PARAGRAPH_LEVEL: usize = 7;
+
 #[derive(Deserialize)]
-struct BaseHtml {
-    // base html only has children.
-    children: Vec<Value>,
+pub(crate) struct BaseHtml {
+    // base html only has children, and they're left out when there are none.
+    #[serde(default)]
+    pub(crate) children: Vec<HtmlNode>,
 }
 
 impl BaseHtml {
-    fn convert_doc_to_struct(doc: &str) -> BaseHtml {
-
-        let json_string: String = Dom::parse(doc).unwrap_or_else(|error| {
-            println!("Couldn't parse the HTML Document: {error}");
-            process::exit(1);
-        }).to_json().unwrap_or_else(|error| {
-            println!("Couldn't parse the Dom Object into JSON: {error}");
-            process::exit(1);
-        });
+    pub(crate) fn convert_doc_to_struct(doc: &str) -> Result<BaseHtml> {
+
+        let json_string: String = Dom::parse(doc)
+            .map_err(|error| Error::Html(error.to_string()))?
+            .to_json()
+            .map_err(|error| Error::Html(error.to_string()))?;
     
-        serde_json::from_str::<BaseHtml>(&json_string).unwrap_or_else(|error| {
-            println!("Couldn't parse the JSON into a valid BaseHtml object: {error}");
-            process::exit(1);
-        })
+        serde_json::from_str::<BaseHtml>(&json_string)
+            .map_err(|error| Error::json("the parsed HTML Document", error))
     }
 }
 
+// text nodes are plain strings, everything else is an element.
 #[derive(Deserialize)]
-struct ChildHtml {
-    // child html have children and a name.
-    children: Vec<Value>,
-    name: String,
+#[serde(untagged)]
+pub(crate) enum HtmlNode {
+    Text(String),
+    Element(ChildHtml),
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ChildHtml {
+    // child html have children and a name, the rest is left out when empty.
+    #[serde(default)]
+    pub(crate) children: Vec<HtmlNode>,
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) attributes: HashMap<String, Option<String>>,
+    #[serde(default)]
+    pub(crate) classes: Vec<String>,
 }
 
 impl ChildHtml {
-    fn is_question_header(&self) -> bool {
-        //self.children.iter();
-        for child in self.children.iter() {
-            if let Some(val) = child.as_str() {
-                if val == "Turn In" || val == "Questions" {
-                    return true;
-                }
-            }
+    // `h2` is 2, anything that isn't a heading is `None`.
+    fn get_heading_level(&self) -> Option<usize> {
+        self.name.strip_prefix('h')
+            .and_then(|x| x.parse::<usize>().ok())
+            .filter(|x| (1..=6).contains(x))
+    }
+
+    // a paragraph that is nothing but bold text, used as a heading.
+    fn is_bold_paragraph(&self) -> bool {
+        self.name == P_KEY && !self.children.is_empty() && self.children.iter().all(|x| match x {
+            HtmlNode::Element(child) => child.name == "strong" || child.name == "b",
+            HtmlNode::Text(text) => text.trim().is_empty(),
+        })
+    }
+
+    // elements that only hold other blocks, the questions could be inside one.
+    fn is_container(&self) -> bool {
+        matches!(self.name.as_str(), "div" | "section" | "article" | "main")
+    }
+
+    pub(crate) fn get_attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).and_then(|x| x.as_deref())
+    }
+}
+
+/// The header tags that can start the questions section by default.
+pub const DEFAULT_HEADER_TAGS: [&str; 3] = ["h1", "h2", "h3"];
+/// The header text that starts the questions section by default.
+pub const DEFAULT_HEADER_PATTERNS: [&str; 4] = ["^turn[ -]?in:?$", "^questions:?$", "^deliverables:?$", "^exercises:?$"];
+/// The lists that hold the questions by default.
+pub const DEFAULT_LIST_TYPES: [&str; 2] = ["ol", "ul"];
+
+/// How to find the questions in an assignment description.
+///
+/// The questions are the lists that follow a header whose tag is one of
+/// `header_tags` (or a paragraph of only bold text, with `bold_paragraphs`)
+/// and whose text matches one of `header_patterns`, case-insensitively.
+/// The section ends at the next heading of the same level or above.
+pub struct QuestionRules {
+    header_tags: Vec<String>,
+    header_patterns: Vec<Regex>,
+    list_types: Vec<String>,
+    bold_paragraphs: bool,
+}
+
+impl Default for QuestionRules {
+    fn default() -> QuestionRules {
+        let to_strings = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+        QuestionRules::new(&to_strings(&DEFAULT_HEADER_TAGS), &to_strings(&DEFAULT_HEADER_PATTERNS), &to_strings(&DEFAULT_LIST_TYPES), true)
+            .expect("the default question rules are valid")
+    }
+}
+
+impl QuestionRules {
+    /// Checks and compiles the rules. Header tags must be `h1`-`h6`, and list types `ol` or `ul`.
+    pub fn new(header_tags: &[String], header_patterns: &[String], list_types: &[String], bold_paragraphs: bool) -> Result<QuestionRules> {
+        let header_tags: Vec<String> = header_tags.iter().map(|x| x.trim().to_lowercase()).collect();
+        if let Some(tag) = header_tags.iter().find(|x| !matches!(x.as_str(), "h1" | "h2" | "h3" | "h4" | "h5" | "h6")) {
+            return Err(Error::Config(format!("Invalid question header tag `{tag}`, it must be one of h1 to h6")));
+        }
+
+        let list_types: Vec<String> = list_types.iter().map(|x| x.trim().to_lowercase()).collect();
+        if let Some(list_type) = list_types.iter().find(|x| *x != OL_KEY && *x != UL_KEY) {
+            return Err(Error::Config(format!("Invalid question list type `{list_type}`, it must be `ol` or `ul`")));
         }
-        false
+
+        let mut patterns: Vec<Regex> = Vec::new();
+        for pattern in header_patterns.iter() {
+            let regex: Regex = RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|error| Error::Config(format!("Invalid question header pattern `{pattern}`: {error}")))?;
+            patterns.push(regex);
+        }
+
+        Ok(QuestionRules { header_tags, header_patterns: patterns, list_types, bold_paragraphs })
+    }
+
+    // the header's level if it starts a questions section.
+    fn get_question_header_level(&self, element: &ChildHtml) -> Option<usize> {
+        let level: usize = if self.header_tags.contains(&element.name) {
+            element.get_heading_level()?
+        } else if self.bold_paragraphs && element.is_bold_paragraph() {
+            BOLD_PARAGRAPH_LEVEL
+        } else {
+            return None;
+        };
+        let text: String = converter::get_text(&element.children);
+        self.header_patterns.iter().any(|x| x.is_match(&text)).then_some(level)
+    }
+
+    // only a heading at the section's level or above ends it, so `Part 2` under `Questions` doesn't.
+    fn is_section_end(&self, element: &ChildHtml, section_level: usize) -> bool {
+        element.get_heading_level().is_some_and(|x| x <= section_level)
+    }
+
+    fn is_question_list(&self, element: &ChildHtml) -> bool {
+        self.list_types.contains(&element.name)
     }
 }
 
@@ -66,11 +168,8 @@ impl ListType {
     fn append_list_string(markdown_str: &mut String, list_type: ListType) {
         match list_type {
             ListType::Ordered {indent: i, num: n} => {
-                if i == 0 {
-                    markdown_str.push('\n');
-                }
                 let num_tabs: String = String::from("    ").repeat(i);
-                markdown_str.push_str(&format!("{num_tabs}{n}. "));
+                markdown_str.push_str(&format!("\n{num_tabs}{n}. "));
             },
             ListType::Unordered {indent: i } => {
                 let num_tabs: String = String::from("    ").repeat(i);
@@ -94,39 +193,66 @@ impl ListType {
     }
 }
 
-
-fn create_markdown_header(assignment_name: &str, user_name: &str) -> String {
-    format!("# {assignment_name}\n#### _By {user_name}_\n\n")
+/// What a lab note is built from, pulled out of an assignment description.
+pub struct ParsedDescription {
+    /// The questions in order, as markdown without their number.
+    pub questions: Vec<String>,
+    /// The whole description converted to markdown, its headings starting at `###`.
+    pub description: String,
 }
 
-// Questions are always Ordered lists in my experience. Otherwise, I'll have to make some changes...
-fn parse_questions(markdown_str: &mut String, question_head: ChildHtml) {
-    markdown_str.push_str("\n## Questions\n");
-    
-    let mut ordered_list: ListType = ListType::Ordered { indent: 0, num: 1 };
+// Questions are always numbered, even when they come from an unordered list.
+fn parse_questions(questions: &mut Vec<String>, question_head: ChildHtml) {
     for child in question_head.children {
-        if let Ok(child_dom) = serde_json::from_value::<ChildHtml>(child) {
+        if let HtmlNode::Element(child_dom) = child {
             if child_dom.name == LI_KEY {
-                parse_list_line(markdown_str, child_dom, ordered_list);
-                markdown_str.push_str("\n\n");
-                ordered_list.increment_list_num();
+                let mut question: String = String::new();
+                parse_list_content(&mut question, child_dom.children, ListType::Ordered { indent: 0, num: 1 });
+                questions.push(question.trim().to_string());
             }
         }
     }
 }
 
-fn parse_list_line(markdown_str: &mut String, line_head: ChildHtml, mut list_type: ListType) {
+// walks the description in order, parsing every question list in a questions section.
+// `section_level` is the level of the header the current section started at, if in one.
+fn find_questions(questions: &mut Vec<String>, nodes: Vec<HtmlNode>, rules: &QuestionRules, section_level: &mut Option<usize>) {
+    for child in nodes {
+        let HtmlNode::Element(child_dom) = child else {
+            continue;
+        };
+        if let Some(level) = rules.get_question_header_level(&child_dom) {
+            *section_level = Some(level);
+        } else if section_level.is_some_and(|x| rules.is_section_end(&child_dom, x)) {
+            *section_level = None;
+        } else if section_level.is_some() && rules.is_question_list(&child_dom) {
+            parse_questions(questions, child_dom);
+        } else if child_dom.is_container() {
+            find_questions(questions, child_dom.children, rules, section_level);
+        }
+    }
+}
+
+fn parse_list_line(markdown_str: &mut String, line_head: ChildHtml, list_type: ListType) {
     if line_head.name == LI_KEY {
         ListType::append_list_string(markdown_str, list_type);
     }
-    
-    for child in line_head.children {
-        if child.is_string() {
-            if let Some(question) = child.as_str() {
-                markdown_str.push_str(question);
-            }
-        } else if child.is_object() {
-            if let Ok(child_dom) = serde_json::from_value::<ChildHtml>(child) {
+    parse_list_content(markdown_str, line_head.children, list_type);
+}
+
+// indents the lines after a line break, so they stay part of the list item instead of starting a paragraph.
+fn push_inline(markdown_str: &mut String, text: &str, list_type: ListType) {
+    let indent: String = String::from("    ").repeat(list_type.get_indent() + 1);
+    markdown_str.push_str(&text.replace('\n', &format!("\n{indent}")));
+}
+
+fn parse_list_content(markdown_str: &mut String, children: Vec<HtmlNode>, mut list_type: ListType) {
+    for child in children {
+        match child {
+            HtmlNode::Text(question) => {
+                push_inline(markdown_str, &converter::decode_entities(&question), list_type);
+            },
+            HtmlNode::Element(child_dom) => {
                 
                 if child_dom.name == P_KEY {
                     // should just be appended as the question, resend it.
@@ -161,32 +287,57 @@ fn parse_list_line(markdown_str: &mut String, line_head: ChildHtml, mut list_typ
                     parse_list_line(markdown_str, child_dom, new_list_type);
 
                 } else {
-                    println!("Unhandled html element: {}", child_dom.name);
-                    println!("\tValue: {:?}", child_dom.children);
+                    // anything else (links, bold, images...) is converted the same way as the description.
+                    push_inline(markdown_str, &converter::convert_inline_element(&child_dom), list_type);
                 }
-            }
+            },
         }
     }
 }
 
-pub fn create_markdown(doc: &str, user_name: &str, assignment_name: String) -> String {
-    let mut markdown_str: String = create_markdown_header(&assignment_name, user_name);
+/// Pulls the questions out of an assignment description with `rules`, and converts the whole description.
+pub fn parse_description(doc: &str, rules: &QuestionRules) -> Result<ParsedDescription> {
+    let base_dom: BaseHtml = BaseHtml::convert_doc_to_struct(doc)?;
 
-    let base_dom: BaseHtml = BaseHtml::convert_doc_to_struct(doc);
+    // the description's headings go under the note's `## Instructions`.
+    let description: String = converter::convert_nodes(&base_dom.children, 2);
 
-    let mut parse_next_list: bool = false;
-    for child in base_dom.children {
-        if let Ok(child_dom) = serde_json::from_value::<ChildHtml>(child) {
-            if child_dom.name == H1_KEY && child_dom.is_question_header() {
-                parse_next_list = true;
-            }
-            // if a question section ever has an unordered list start, edit it here.
-            if parse_next_list && child_dom.name == OL_KEY {
-                parse_questions(&mut markdown_str, child_dom);
-            }
-        }
-    }
-    markdown_str
+    let mut questions: Vec<String> = Vec::new();
+    let mut section_level: Option<usize> = None;
+    find_questions(&mut questions, base_dom.children, rules, &mut section_level);
 
+    Ok(ParsedDescription { questions, description })
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_questions(doc: &str) -> Vec<String> {
+        parse_description(doc, &QuestionRules::default()).unwrap().questions
+    }
+
+    #[test]
+    fn line_break_stays_in_the_question() {
+        let doc: &str = "<h2>Questions</h2><ol><li>Line<br>break<ul><li>Sub<br>item</li></ul></li></ol>";
+        assert_eq!(get_questions(doc), vec!{"Line\\\n    break\n    - Sub\\\n        item"});
+    }
+
+    #[test]
+    fn bold_paragraph_doesnt_end_the_section() {
+        let doc: &str = "<h2>Questions</h2><ol><li>First q</li></ol><p><strong>Part 2</strong></p><ol><li>Later q</li></ol>";
+        assert_eq!(get_questions(doc), vec!{"First q", "Later q"});
+    }
+
+    #[test]
+    fn section_ends_at_a_heading_of_the_same_level() {
+        let doc: &str = "<h2>Questions</h2><h3>Part 1</h3><ol><li>First q</li></ol><h2>Resources</h2><ol><li>A link</li></ol>";
+        assert_eq!(get_questions(doc), vec!{"First q"});
+    }
+
+    #[test]
+    fn bold_paragraph_can_start_the_section() {
+        let doc: &str = "<p><strong>Questions:</strong></p><ol><li>First q</li></ol><h3>Notes</h3><ol><li>Not a q</li></ol>";
+        assert_eq!(get_questions(doc), vec!{"First q"});
+    }
+}
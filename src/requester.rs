@@ -1,46 +1,472 @@
-use inquire::Select;
-use reqwest::{Client, Response};
-use serde::Deserialize;
+use rand::Rng;
+use reqwest::{Client, ClientBuilder, RequestBuilder, Response, StatusCode, Url};
+use reqwest::multipart::{Form, Part};
+use reqwest::redirect::Policy;
+use chrono::{DateTime, Local, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
-use std::{fmt, process};
+use std::{fmt, fs};
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
+use crate::cache::{CachedResponse, ResponseCache};
+use crate::error::{Error, Result};
+use crate::oauth::OAuthSession;
 
-pub enum ApiEndpoint {
-    CourseList,
-    AssignmentGroupList(i32),
-    AssignmentList(i32, i32),
-    Assignment(i32, i32),
+
+enum ApiEndpoint {
+    // with past courses or not.
+    CourseList(bool),
+    AssignmentGroupList(i64),
+    // a course's assignments, only the ones in a group if there is one.
+    AssignmentList(i64, Option<i64>),
+    Submissions(i64, i64),
+    SubmissionFiles(i64, i64),
+    UserSelf,
 }
 
 impl ApiEndpoint {
-    fn get_url(endpoint: ApiEndpoint) -> String {
-        let uri: String = String::from("https://canvas.cse.taylor.edu/api/v1");
+    fn get_url(canvas_url: &str, endpoint: ApiEndpoint) -> String {
+        let uri: String = format!("{canvas_url}/api/v1");
         match endpoint {
-            ApiEndpoint::CourseList                         => format!("{uri}/courses"),
+            ApiEndpoint::CourseList(_)                      => format!("{uri}/courses"),
             ApiEndpoint::AssignmentGroupList(id)       => format!("{uri}/courses/{id}/assignment_groups"),
-            ApiEndpoint::AssignmentList(cid, gid) => format!("{uri}/courses/{cid}/assignment_groups/{gid}/assignments"),
-            ApiEndpoint::Assignment(cid, aid)     => format!("{uri}/courses/{cid}/assignments/{aid}"),
+            ApiEndpoint::AssignmentList(cid, Some(gid)) => format!("{uri}/courses/{cid}/assignment_groups/{gid}/assignments"),
+            ApiEndpoint::AssignmentList(cid, None) => format!("{uri}/courses/{cid}/assignments"),
+            ApiEndpoint::Submissions(cid, aid)    => format!("{uri}/courses/{cid}/assignments/{aid}/submissions"),
+            ApiEndpoint::SubmissionFiles(cid, aid) => format!("{uri}/courses/{cid}/assignments/{aid}/submissions/self/files"),
+            ApiEndpoint::UserSelf                          => format!("{uri}/users/self"),
+        }
+    }
+
+    // extra query parameters an endpoint always needs.
+    fn get_query(&self) -> Vec<(&'static str, String)> {
+        match self {
+            ApiEndpoint::CourseList(include_past) => {
+                let mut query: Vec<(&str, String)> = vec!{("include[]", String::from("term"))};
+                if !include_past {
+                    query.push(("enrollment_state", String::from("active")));
+                    query.push(("state[]", String::from("available")));
+                }
+                query
+            },
+            // the user's own submission, to tell what's already been handed in.
+            ApiEndpoint::AssignmentList(_, _) => vec![("include[]", String::from("submission"))],
+            _ => Vec::new(),
         }
     }
 }
 
-async fn response_to_json(response: Response) -> Result<Value, reqwest::Error> {
-    response.json().await
+/// Checks that `canvas_url` is an http(s) url, and strips a trailing `/` or `/api/v1`.
+pub fn parse_canvas_url(canvas_url: &str) -> Result<String> {
+    let canvas_url: &str = canvas_url.trim().trim_end_matches('/');
+    let canvas_url: &str = canvas_url.strip_suffix("/api/v1").unwrap_or(canvas_url);
+
+    match Url::parse(canvas_url) {
+        Ok(url) if (url.scheme() == "https" || url.scheme() == "http") && url.has_host() => Ok(canvas_url.to_string()),
+        Ok(_) => Err(Error::Config(format!("Invalid `canvas_url`: {canvas_url}\nIt must be an http(s) url, like https://canvas.example.edu"))),
+        Err(error) => Err(Error::Config(format!("Invalid `canvas_url`: {canvas_url}: {error}\nIt must be an http(s) url, like https://canvas.example.edu"))),
+    }
 }
 
-async fn get_response(client: &Client, token: &str, endpoint: ApiEndpoint) -> Result<Value, reqwest::Error> {
-    // create the API url based on the API Endpoint type.
-    let url: String = ApiEndpoint::get_url(endpoint);
+async fn response_to_json(response: Response) -> Result<Value> {
+    Ok(response.json().await?)
+}
 
-    // get the response from the server.
-    let response = client.get(&url)
-        .header("Authorization", format!("Bearer {token}"))
-        .send()
-        .await?;
+// turns a non-success status into an error, keeping canvas' error body.
+// 304 is only ever the answer to a conditional request, which handles it.
+async fn check_status(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() || status == StatusCode::NOT_MODIFIED {
+        return Ok(response);
+    }
+    let url: String = response.url().to_string();
+    let body: String = response.text().await.unwrap_or_default();
+    Err(Error::Status { status, url, body })
+}
 
-    assert!(response.status().is_success());
+// finds the `rel="next"` url in a canvas `Link` header, if there is one.
+fn get_next_link(response: &Response) -> Option<String> {
+    let link: &str = response.headers().get("Link")?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let mut pieces = part.split(';');
+        let url: &str = pieces.next()?.trim().trim_start_matches('<').trim_end_matches('>');
+        if pieces.any(|x| x.trim() == "rel=\"next\"") {
+            Some(url.to_string())
+        } else {
+            None
+        }
+    })
+}
 
-    response_to_json(response).await
+// `Retry-After` is either a number of seconds or an http date.
+fn get_retry_after(response: &Response) -> Option<Duration> {
+    let value: &str = response.headers().get("Retry-After")?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    let date: DateTime<Utc> = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((date - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+}
+
+// how much of canvas' rate limit bucket is left, if it said.
+fn get_rate_limit_remaining(response: &Response) -> Option<f64> {
+    response.headers().get("X-Rate-Limit-Remaining")?.to_str().ok()?.trim().parse().ok()
+}
+
+// busy or briefly down, worth another try. Canvas answers a throttled request with 403 and an empty bucket.
+fn is_retryable(response: &Response) -> bool {
+    match response.status() {
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT => true,
+        StatusCode::FORBIDDEN => get_rate_limit_remaining(response).is_some_and(|x| x <= 0.0),
+        _ => false,
+    }
+}
+
+/// How failed GET requests are tried again.
+///
+/// Timeouts, connection errors, 429, 502, 503 and 504 are retried, waiting
+/// `Retry-After` if Canvas sends it, otherwise `base_delay` doubled for each
+/// try with some jitter. Other requests are never retried, since sending them twice could submit twice.
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    /// How many times a request is tried again, 0 to never retry.
+    pub max_retries: u32,
+    /// The wait before the first retry.
+    pub base_delay: Duration,
+    /// The longest wait between tries. If `Retry-After` asks for longer, the request fails instead.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // between half and all of `base_delay * 2^attempt`, so clients that failed together don't retry together.
+    fn get_backoff(&self, attempt: u32) -> Duration {
+        let delay: Duration = self.base_delay.saturating_mul(2_u32.saturating_pow(attempt)).min(self.max_delay);
+        let jitter: f64 = rand::thread_rng().gen_range(0.0..=1.0);
+        delay.mul_f64(0.5 + jitter / 2.0)
+    }
+}
+
+
+/*
+ * CLIENT
+ */
+
+/// A connection to one Canvas instance, authenticated with an access token.
+pub struct CanvasClient {
+    client: Client,
+    canvas_url: String,
+    // replaced when an OAuth session refreshes it.
+    token: RwLock<String>,
+    oauth: Option<Arc<OAuthSession>>,
+    per_page: u32,
+    timeout: Duration,
+    retry_policy: RetryPolicy,
+    // the last `X-Rate-Limit-Remaining`, to slow down before canvas starts refusing.
+    rate_limit_remaining: Mutex<Option<f64>>,
+    cache: Option<ResponseCache>,
+    offline: bool,
+}
+
+impl CanvasClient {
+    /// Canvas only sends 10 items per page by default.
+    pub const DEFAULT_PER_PAGE: u32 = 50;
+    /// How long a request can take before it's given up on (and maybe retried).
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Below this much of Canvas' rate limit bucket (700 when full), requests are spaced out.
+    const RATE_LIMIT_LOW: f64 = 100.0;
+    /// The longest pause between requests when the bucket is nearly empty.
+    const RATE_LIMIT_PAUSE: Duration = Duration::from_secs(2);
+
+    /// Creates a client for `canvas_url` (like `https://canvas.example.edu`).
+    pub fn new(canvas_url: &str, token: &str) -> Result<CanvasClient> {
+        CanvasClient::with_client(ClientBuilder::new().build()?, canvas_url, token)
+    }
+
+    /// Creates a client that sends its requests through an existing `reqwest::Client`.
+    pub fn with_client(client: Client, canvas_url: &str, token: &str) -> Result<CanvasClient> {
+        Ok(CanvasClient {
+            client,
+            canvas_url: parse_canvas_url(canvas_url)?,
+            token: RwLock::new(token.to_string()),
+            oauth: None,
+            per_page: CanvasClient::DEFAULT_PER_PAGE,
+            timeout: CanvasClient::DEFAULT_TIMEOUT,
+            retry_policy: RetryPolicy::default(),
+            rate_limit_remaining: Mutex::new(None),
+            cache: None,
+            offline: false,
+        })
+    }
+
+    /// Refreshes the token through `session` whenever Canvas answers 401.
+    pub fn with_oauth(mut self, session: OAuthSession) -> CanvasClient {
+        self.oauth = Some(Arc::new(session));
+        self
+    }
+
+    /// Sets how many items are requested per page when fetching lists.
+    pub fn per_page(mut self, per_page: u32) -> CanvasClient {
+        self.per_page = per_page;
+        self
+    }
+
+    /// Sets how long a request can take.
+    pub fn timeout(mut self, timeout: Duration) -> CanvasClient {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how failed GET requests are retried.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> CanvasClient {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Stores every GET response in `cache`, and asks Canvas whether it changed before using it again.
+    pub fn with_cache(mut self, cache: ResponseCache) -> CanvasClient {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Only reads from the cache, never from Canvas. Anything that isn't cached, and submitting, fails.
+    pub fn offline(mut self, offline: bool) -> CanvasClient {
+        self.offline = offline;
+        self
+    }
+
+    /// Whether it only reads from the cache.
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// The root url of the Canvas instance.
+    pub fn canvas_url(&self) -> &str {
+        &self.canvas_url
+    }
+
+    fn get_token(&self) -> String {
+        match self.token.read() {
+            Ok(token) => token.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    // waits a little when the rate limit bucket is nearly empty, longer the emptier it is.
+    async fn wait_for_rate_limit(&self) {
+        let remaining: Option<f64> = self.rate_limit_remaining.lock().ok().and_then(|x| *x);
+        if let Some(remaining) = remaining.filter(|x| *x < CanvasClient::RATE_LIMIT_LOW) {
+            let emptiness: f64 = (1.0 - remaining.max(0.0) / CanvasClient::RATE_LIMIT_LOW).clamp(0.0, 1.0);
+            tokio::time::sleep(CanvasClient::RATE_LIMIT_PAUSE.mul_f64(emptiness)).await;
+        }
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        self.wait_for_rate_limit().await;
+        let response: Response = request.timeout(self.timeout).send().await?;
+        if let Some(remaining) = get_rate_limit_remaining(&response) {
+            if let Ok(mut current) = self.rate_limit_remaining.lock() {
+                *current = Some(remaining);
+            }
+        }
+        Ok(response)
+    }
+
+    // sends the request with the token. With OAuth, an expired token is refreshed and the request sent again.
+    async fn send_authorized(&self, build: impl Fn(&str) -> RequestBuilder) -> Result<Response> {
+        let token: String = self.get_token();
+        let response: Response = self.send(build(&token)).await?;
+
+        let Some(oauth) = &self.oauth else {
+            return Ok(response);
+        };
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        let token: String = oauth.refresh(&token).await?;
+        if let Ok(mut current) = self.token.write() {
+            *current = token.clone();
+        }
+        self.send(build(&token)).await
+    }
+
+    // GETs can be sent again safely, so failures that might pass are retried, see `RetryPolicy`.
+    async fn send_get(&self, url: &str, query: &[(&str, String)], headers: &[(&str, String)]) -> Result<Response> {
+        let mut attempt: u32 = 0;
+        loop {
+            let result: Result<Response> = self.send_authorized(|token| {
+                let mut request: RequestBuilder = self.client.get(url)
+                    .bearer_auth(token)
+                    .query(query);
+                for (name, value) in headers.iter() {
+                    request = request.header(*name, value);
+                }
+                request
+            }).await;
+
+            let delay: Option<Duration> = match &result {
+                Ok(response) if is_retryable(response) => {
+                    Some(get_retry_after(response).unwrap_or_else(|| self.retry_policy.get_backoff(attempt)))
+                },
+                Err(Error::Request(error)) if error.is_timeout() || error.is_connect() => Some(self.retry_policy.get_backoff(attempt)),
+                _ => None,
+            };
+            match delay {
+                Some(delay) if attempt < self.retry_policy.max_retries && delay <= self.retry_policy.max_delay => {
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                },
+                _ => return check_status(result?).await,
+            }
+        }
+    }
+
+    // one page, revalidated against the cache, or only from the cache when offline.
+    // Returns the page and the url of the next one.
+    async fn get_page(&self, url: &str, query: &[(&str, String)]) -> Result<(Value, Option<String>)> {
+        // the cache is keyed by the whole url, query included.
+        let url: String = match query.is_empty() {
+            true => url.to_string(),
+            false => Url::parse_with_params(url, query).map(String::from).unwrap_or_else(|_| url.to_string()),
+        };
+        let cached: Option<CachedResponse> = self.cache.as_ref().and_then(|x| x.get(&url));
+        if self.offline {
+            return cached.map(|x| (x.body, x.next_url))
+                .ok_or_else(|| Error::Offline(format!("{url} isn't cached, run `maker cache sync` while online")));
+        }
+
+        let mut headers: Vec<(&str, String)> = Vec::new();
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                headers.push(("If-None-Match", etag.clone()));
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                headers.push(("If-Modified-Since", last_modified.clone()));
+            }
+        }
+        let response: Response = self.send_get(&url, &[], &headers).await?;
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (response.status(), cached) {
+            return Ok((cached.body, cached.next_url));
+        }
+
+        let get_header = |name: &str| response.headers().get(name).and_then(|x| x.to_str().ok()).map(String::from);
+        let etag: Option<String> = get_header("ETag");
+        let last_modified: Option<String> = get_header("Last-Modified");
+        let next_url: Option<String> = get_next_link(&response);
+        let body: Value = response_to_json(response).await?;
+
+        if let Some(cache) = &self.cache {
+            let cached: CachedResponse = CachedResponse { url, etag, last_modified, fetched_at: Utc::now(), next_url, body };
+            // a cache that can't be written only makes the next run slower.
+            let _ = cache.put(&cached);
+            return Ok((cached.body, cached.next_url));
+        }
+        Ok((body, next_url))
+    }
+
+    async fn get_response(&self, endpoint: ApiEndpoint) -> Result<Value> {
+        // create the API url based on the API Endpoint type.
+        let query: Vec<(&str, String)> = endpoint.get_query();
+        let url: String = ApiEndpoint::get_url(&self.canvas_url, endpoint);
+
+        let (response, _) = self.get_page(&url, &query).await?;
+        Ok(response)
+    }
+
+    async fn get_paginated_response(&self, endpoint: ApiEndpoint) -> Result<Value> {
+        // create the API url based on the API Endpoint type.
+        let mut query: Vec<(&str, String)> = endpoint.get_query();
+        query.push(("per_page", self.per_page.to_string()));
+        let url: String = ApiEndpoint::get_url(&self.canvas_url, endpoint);
+
+        let (first_page, mut next_url) = self.get_page(&url, &query).await?;
+        // not a list, nothing to merge.
+        let Value::Array(mut items) = first_page else {
+            return Ok(first_page);
+        };
+        // the next link already carries the query, so don't add it again.
+        while let Some(url) = next_url {
+            let (page, next_page_url) = self.get_page(&url, &[]).await?;
+            // a later page has to be a list too, or the ones before it would be lost.
+            let page: Vec<Value> = serde_json::from_value(page)
+                .map_err(|error| Error::json("a page of a paginated response", error))?;
+            items.extend(page);
+            next_url = next_page_url;
+        }
+
+        Ok(Value::Array(items))
+    }
+
+    async fn post_response(&self, endpoint: ApiEndpoint, form: &[(&str, String)]) -> Result<Value> {
+        if self.offline {
+            return Err(Error::Offline(String::from("Can't send anything to Canvas while offline")));
+        }
+        // create the API url based on the API Endpoint type.
+        let url: String = ApiEndpoint::get_url(&self.canvas_url, endpoint);
+
+        // post the form to the server.
+        let response: Response = self.send_authorized(|token| {
+            self.client.post(&url)
+                .bearer_auth(token)
+                .form(form)
+        }).await?;
+
+        let response: Response = check_status(response).await?;
+
+        response_to_json(response).await
+    }
+}
+
+
+/*
+ * USER
+ */
+
+/// The user a token belongs to.
+#[derive(Deserialize)]
+pub struct User {
+    pub id: i64,
+    pub name: String,
+}
+
+impl User {
+    /// Fetches the user the client's token belongs to, which also checks that the token works.
+    pub async fn get_self(client: &CanvasClient) -> Result<User> {
+        let user_json: Value = client.get_response(ApiEndpoint::UserSelf).await?;
+        serde_json::from_value::<User>(user_json).map_err(|error| Error::json("the user", error))
+    }
+}
+
+
+// deserializes each item on its own, warning about and skipping the ones that don't fit.
+fn get_valid_items<T: DeserializeOwned>(items: Vec<Value>, kind: &str) -> Vec<T> {
+    let mut valid: Vec<T> = Vec::new();
+    for item in items.into_iter() {
+        let name: String = match (&item["name"], &item["id"]) {
+            (Value::String(name), _) => format!("`{name}`"),
+            (_, Value::Null) => String::from("without an id"),
+            (_, id) => id.to_string(),
+        };
+        match serde_json::from_value::<T>(item) {
+            Ok(x) => valid.push(x),
+            Err(error) => eprintln!("Skipped the {kind} {name}, Canvas sent something unexpected: {error}"),
+        }
+    }
+    valid
 }
 
 
@@ -48,64 +474,64 @@ async fn get_response(client: &Client, token: &str, endpoint: ApiEndpoint) -> Re
  * COURSE
  */
 
+/// A course the user is enrolled in.
 #[derive(Deserialize)]
 pub struct Course {
-    pub id: i32,
-    // is_public_to_auth_users: bool,
+    pub id: i64,
+    pub name: String,
+    /// The short code, like `COS 120`.
+    #[serde(default)]
+    pub course_code: String,
+    /// The term the course is in, if it has one.
+    #[serde(default)]
+    pub term: Option<Term>,
+}
+
+/// The term (semester) a course belongs to.
+#[derive(Deserialize)]
+pub struct Term {
+    pub id: i64,
     pub name: String,
 }
 
 impl fmt::Display for Course {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.name)
+        match &self.term {
+            Some(term) => write!(f, "{} ({})", self.name, term.name),
+            None => write!(f, "{}", self.name),
+        }
     }
 }
 
 impl Course {
     fn get_all_courses(courses_json: Value) -> Vec<Course> {
-        let mut courses: Vec<Course> = Vec::new();
-        if let Value::Array(items) = courses_json {
-            // parse all courses.
-            for item in items.into_iter() {
-                if let Ok(course) = serde_json::from_value::<Course>(item) {
-                    // use this when I actually have public courses.
-                    // if course.is_public_to_auth_users {
-                    //     courses.push(course);
-                    // }
-                    // for now, just push all :(
-                    courses.push(course);
-                }
-            }
-        }
-        courses
-    }
-
-    fn choose_course(courses: Vec<Course>) -> Course {
-        Select::new("Which course would you like to select?", courses)
-            .prompt()
-            .unwrap_or_else(|_| {
-            println!("Exiting.");
-            process::exit(1);
-        })
+        let Value::Array(items) = courses_json else {
+            return Vec::new();
+        };
+        // courses the user can't access anymore come with only an id, they're not worth a warning.
+        let items: Vec<Value> = items.into_iter()
+            .filter(|x| x["access_restricted_by_date"] != Value::Bool(true))
+            .collect();
+        get_valid_items(items, "course")
     }
 
-    pub async fn get_course(client: &Client, token: &str) -> Course {
-        
-        let courses_json: Value = get_response(client, token, ApiEndpoint::CourseList)
-            .await.unwrap_or_else(|error| {
-            println!("Course Endpoint failed: {error}");
-            process::exit(1);
-        });
-        let courses: Vec<Course> = Course::get_all_courses(courses_json);
-        Course::choose_course(courses)
+    /// Fetches the courses the user is enrolled in, with `include_past` also the
+    /// completed ones and the ones the enrollment has ended in.
+    pub async fn get_courses(client: &CanvasClient, include_past: bool) -> Result<Vec<Course>> {
+        let courses_json: Value = client.get_paginated_response(ApiEndpoint::CourseList(include_past)).await?;
+        Ok(Course::get_all_courses(courses_json))
     }
 }
 
 
+/// The group names used when `assignment_groups` isn't set, as case-insensitive regexes.
+pub const DEFAULT_ASSIGNMENT_GROUPS: [&str; 1] = ["^labs?( & homework)?$"];
+
+/// A group of assignments in a course, like "Labs" or "Exams".
 #[derive(Deserialize)]
-struct AssignmentGroup {
-    id: i32,
-    name: String,
+pub struct AssignmentGroup {
+    pub id: i64,
+    pub name: String,
 }
 
 impl fmt::Display for AssignmentGroup {
@@ -115,109 +541,511 @@ impl fmt::Display for AssignmentGroup {
 }
 
 impl AssignmentGroup {
-    fn get_lab_group(assignment_group_json: Value) -> Option<i32> {
-        let mut assignment_groups: Vec<AssignmentGroup> = Vec::new();
-        if let Value::Array(items) = assignment_group_json {
-            for item in items.into_iter() {
-                if let Ok(group) = serde_json::from_value::<AssignmentGroup>(item) {
-                    assignment_groups.push(group);
-                }
-            }
-            if let Some(group) = assignment_groups.into_iter().find(|x| x.name == "Labs & Homework") {
-                return Some(group.id);
-            }
-        }
-        None
+    /// Fetches every assignment group in a course.
+    pub async fn get_groups(client: &CanvasClient, course_id: i64) -> Result<Vec<AssignmentGroup>> {
+        let assignment_groups_json: Value = client.get_paginated_response(ApiEndpoint::AssignmentGroupList(course_id)).await?;
+        serde_json::from_value::<Vec<AssignmentGroup>>(assignment_groups_json)
+            .map_err(|error| Error::json("the assignment groups", error))
+    }
+
+    /// The groups whose name matches any of `patterns`.
+    pub fn find_groups<'a>(groups: &'a [AssignmentGroup], patterns: &[Regex]) -> Vec<&'a AssignmentGroup> {
+        groups.iter()
+            .filter(|x| patterns.iter().any(|pattern| pattern.is_match(x.name.trim())))
+            .collect()
     }
 }
 
 
-#[derive(Deserialize)]
+/// One criterion of an assignment's rubric. Canvas leaves out what the rubric's
+/// author didn't fill in, so everything has a default.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct RubricCriterion {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    pub long_description: Option<String>,
+    #[serde(default)]
+    pub points: f64,
+    #[serde(default)]
+    pub ratings: Vec<RubricRating>,
+}
+
+/// A rating a rubric criterion can be given.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct RubricRating {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub points: f64,
+}
+
+/// The user's own submission for an assignment.
+#[derive(Deserialize, Clone)]
+pub struct SubmissionStatus {
+    /// Like `unsubmitted`, `submitted`, `pending_review` or `graded`.
+    #[serde(default)]
+    pub workflow_state: String,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub score: Option<f64>,
+    pub grade: Option<String>,
+}
+
+/// An assignment in a course's lab group, as Canvas describes it.
+#[derive(Deserialize, Clone)]
 pub struct Assignment {
-    pub id: i32,
-    // has_submitted_submissions: bool,
+    pub id: i64,
     pub name: String,
+    /// The description HTML, `None` while the assignment is locked.
+    pub description: Option<String>,
+    pub due_at: Option<DateTime<Utc>>,
+    /// When the assignment stops accepting submissions.
+    pub lock_at: Option<DateTime<Utc>>,
+    /// When the assignment becomes available.
+    pub unlock_at: Option<DateTime<Utc>>,
+    pub points_possible: Option<f64>,
+    /// Like `online_text_entry` or `online_upload`.
+    #[serde(default)]
+    pub submission_types: Vec<String>,
+    /// The file extensions an upload may have, any when empty.
+    #[serde(default)]
+    pub allowed_extensions: Vec<String>,
+    /// The assignment's page on Canvas.
+    pub html_url: Option<String>,
+    #[serde(default)]
+    pub rubric: Vec<RubricCriterion>,
+    /// Whether anyone has submitted yet.
+    #[serde(default)]
+    pub has_submitted_submissions: bool,
+    #[serde(default)]
+    pub locked_for_user: bool,
+    /// Why the assignment is locked, if it is.
+    pub lock_explanation: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// The assignment group it's in.
+    pub assignment_group_id: Option<i64>,
+    /// The user's submission, if Canvas included it.
+    pub submission: Option<SubmissionStatus>,
 }
 
 impl fmt::Display for Assignment {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.name)
+        write!(f, "{}", self.name)?;
+        let due: String = self.get_local_due_date().unwrap_or_else(|| String::from("no due date"));
+        match self.get_points() {
+            Some(points) => write!(f, " (due {due}, {points} pts)"),
+            None => write!(f, " (due {due})"),
+        }
     }
 }
 
 impl Assignment {
-    fn get_all_assignments(assignments_json: Value) -> Vec<Assignment> {
-        let mut assignments: Vec<Assignment> = Vec::new();
-        if let Value::Array(items) = assignments_json {
-            // parse all assignments.
-            for item in items.into_iter() {
-                if let Ok(assignment) = serde_json::from_value::<Assignment>(item) {
-                    /*
-                    For now, append all assignments.
-                    Later, use has_submitted_submissions
-                    to check if there have been any submissions.
-                    There may be another value to track instead.
-                    For now, add all assignments.
-                     */
-                    assignments.push(assignment);
-                }
-            }
+    // one assignment Canvas describes oddly is skipped, so it doesn't hide the rest of the course.
+    fn get_all_assignments(assignments_json: Value) -> Result<Vec<Assignment>> {
+        let items: Vec<Value> = serde_json::from_value::<Vec<Value>>(assignments_json)
+            .map_err(|error| Error::json("the assignments", error))?;
+        Ok(get_valid_items(items, "assignment"))
+    }
+
+    /// Fetches every assignment in an assignment group, or with `None` every assignment in the course.
+    pub async fn get_assignments(client: &CanvasClient, course_id: i64, group_id: Option<i64>) -> Result<Vec<Assignment>> {
+        let assignments_json: Value = client.get_paginated_response(ApiEndpoint::AssignmentList(course_id, group_id)).await?;
+        Assignment::get_all_assignments(assignments_json)
+    }
+
+    /// The description, or why there isn't one yet.
+    pub fn get_description(&self) -> Result<&str> {
+        self.description.as_deref().ok_or_else(|| {
+            let reason: &str = self.lock_explanation.as_deref().unwrap_or("it may not be accessible yet");
+            Error::NotFound(format!("{} has no description: {reason}", self.name))
+        })
+    }
+
+    /// The due date in the user's time zone, like `2026-10-11 23:59`.
+    pub fn get_local_due_date(&self) -> Option<String> {
+        self.due_at.map(|x| x.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+    }
+
+    /// Whether the user has handed it in.
+    pub fn is_submitted(&self) -> bool {
+        self.submission.as_ref().is_some_and(|x| x.submitted_at.is_some())
+    }
+
+    /// The points, without a trailing `.0`.
+    pub fn get_points(&self) -> Option<String> {
+        self.points_possible.map(|x| if x.fract() == 0.0 { format!("{x:.0}") } else { x.to_string() })
+    }
+}
+
+
+
+/*
+ * SUBMISSION
+ */
+
+/// The ways a lab note can be submitted.
+#[derive(PartialEq, Clone, Copy)]
+pub enum SubmissionType {
+    OnlineTextEntry,
+    OnlineUpload,
+}
+
+impl fmt::Display for SubmissionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SubmissionType::OnlineTextEntry => write!(f, "Text Entry"),
+            SubmissionType::OnlineUpload => write!(f, "File Upload"),
         }
-        assignments
-    }
-
-    fn choose_assignment(assignments: Vec<Assignment>) -> Assignment {
-        let answer: Assignment = Select::new("Which assignment would you like to make a lab note for?", assignments)
-            .prompt()
-            .unwrap_or_else(|_| {
-            println!("Exiting.");
-            process::exit(1);
-        });
-        answer
-    }
-    pub async fn get_assignment(client: &Client, token: &str, course_id: i32) -> Assignment {
-        let assignment_groups_json: Value = get_response(client, token, ApiEndpoint::AssignmentGroupList(course_id))
-            .await.unwrap_or_else(|error| {
-            println!("Assignment Group Endpoint failed: {error}");
-            process::exit(1);
-        });
-        if let Some(group_id) = AssignmentGroup::get_lab_group(assignment_groups_json) {
-
-            let assignments_json: Value = get_response(client, token, ApiEndpoint::AssignmentList(course_id, group_id))
-                .await.unwrap_or_else(|error| {
-                println!("Assignment Endpoint failed: {error}");
-                process::exit(1);
-            });
-            let assignments: Vec<Assignment> = Assignment::get_all_assignments(assignments_json);
-            Assignment::choose_assignment(assignments)
-        } else {
-            println!("Error: No lab group for this class");
-            process::exit(1);
+    }
+}
+
+impl SubmissionType {
+    fn get_api_name(&self) -> &'static str {
+        match *self {
+            SubmissionType::OnlineTextEntry => "online_text_entry",
+            SubmissionType::OnlineUpload => "online_upload",
         }
     }
+
+    /// The submission types we know how to send that this assignment accepts.
+    /// Uploads are only offered if a `.md` file is allowed.
+    pub fn get_supported_types(assignment: &Assignment) -> Vec<SubmissionType> {
+        let allows_markdown: bool = assignment.allowed_extensions.is_empty()
+            || assignment.allowed_extensions.iter().any(|x| x.trim_start_matches('.').eq_ignore_ascii_case("md"));
+        [SubmissionType::OnlineTextEntry, SubmissionType::OnlineUpload]
+            .into_iter()
+            .filter(|x| assignment.submission_types.iter().any(|t| t == x.get_api_name()))
+            .filter(|x| *x != SubmissionType::OnlineUpload || allows_markdown)
+            .collect()
+    }
 }
 
+#[derive(Deserialize)]
+struct UploadTicket {
+    upload_url: String,
+    upload_params: serde_json::Map<String, Value>,
+}
 
 #[derive(Deserialize)]
-pub struct AssignmentData {
-     pub description: String,
+struct UploadedFile {
+    id: i64,
 }
 
-impl AssignmentData {
-    fn get_assignment_stuff(assignment_json: Value) -> AssignmentData {
-        serde_json::from_value::<AssignmentData>(assignment_json).unwrap_or_else(|error| {
-            println!("Assignment may not be accesible yet.\nError: {error}");
-            process::exit(1);
-        })
+/// A submission canvas accepted.
+#[derive(Deserialize)]
+pub struct Submission {
+    pub id: i64,
+    pub submitted_at: Option<String>,
+}
+
+impl Submission {
+    fn get_submission(submission_json: Value) -> Result<Submission> {
+        serde_json::from_value::<Submission>(submission_json)
+            .map_err(|error| Error::json("the submission response", error))
     }
 
-    pub async fn get_assignment_data(client: &Client, token: &str, course_id: i32, assignment_id: i32) -> AssignmentData {
-        let assignment_json: Value = get_response(client, token, ApiEndpoint::Assignment(course_id, assignment_id))
-            .await.unwrap_or_else(|error| {
-            println!("Assignment Data Endpoint failed: {error}");
-            process::exit(1);
-        });
-        AssignmentData::get_assignment_stuff(assignment_json)
+    // Canvas expects an HTML body, so keep the markdown as preformatted text.
+    fn markdown_to_body(content: &str) -> String {
+        let escaped: String = content
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        format!("<pre>{escaped}</pre>")
+    }
+
+    async fn upload_file(client: &CanvasClient, course_id: i64, assignment_id: i64, file_path: &Path) -> Result<i64> {
+        let file_name: String = file_path.file_name()
+            .and_then(|x| x.to_str())
+            .unwrap_or("lab_note.md")
+            .to_string();
+        let content: Vec<u8> = fs::read(file_path).map_err(|error| Error::io(file_path, error))?;
+
+        // step 1: tell canvas about the file, it answers with where to upload it.
+        let form: [(&str, String); 3] = [
+            ("name", file_name.clone()),
+            ("size", content.len().to_string()),
+            ("content_type", String::from("text/markdown")),
+        ];
+        let ticket_json: Value = client.post_response(ApiEndpoint::SubmissionFiles(course_id, assignment_id), &form).await?;
+        let ticket: UploadTicket = serde_json::from_value(ticket_json)
+            .map_err(|error| Error::json("the upload ticket", error))?;
+
+        // step 2: upload the file. The upload params must come before the file itself.
+        let mut upload_form: Form = Form::new();
+        for (key, value) in ticket.upload_params.into_iter() {
+            let value: String = match value {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            upload_form = upload_form.text(key, value);
+        }
+        upload_form = upload_form.part("file", Part::bytes(content).file_name(file_name));
+
+        // redirects are followed by hand, since the confirmation needs the token.
+        let upload_client: Client = ClientBuilder::new()
+            .redirect(Policy::none())
+            .build()?;
+        let response: Response = upload_client.post(&ticket.upload_url)
+            .multipart(upload_form)
+            .timeout(client.timeout)
+            .send()
+            .await?;
+
+        // step 3: confirm the upload if canvas redirected us.
+        let response: Response = if response.status().is_redirection() {
+            let location: String = response.headers()
+                .get("Location")
+                .and_then(|x| x.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            client.send_get(&location, &[], &[]).await?
+        } else {
+            check_status(response).await?
+        };
+
+        let file: UploadedFile = serde_json::from_value(response_to_json(response).await?)
+            .map_err(|error| Error::json("the uploaded file response", error))?;
+        Ok(file.id)
+    }
+
+    /// Submits the lab note at `file_path` as the user's submission for the assignment.
+    pub async fn submit_lab_note(client: &CanvasClient, course_id: i64, assignment_id: i64, submission_type: SubmissionType, file_path: &Path) -> Result<Submission> {
+        let mut form: Vec<(&str, String)> = vec![
+            ("submission[submission_type]", submission_type.get_api_name().to_string()),
+        ];
+
+        match submission_type {
+            SubmissionType::OnlineTextEntry => {
+                let content: String = fs::read_to_string(file_path).map_err(|error| Error::io(file_path, error))?;
+                form.push(("submission[body]", Submission::markdown_to_body(&content)));
+            },
+            SubmissionType::OnlineUpload => {
+                let file_id: i64 = Submission::upload_file(client, course_id, assignment_id, file_path).await?;
+                form.push(("submission[file_ids][]", file_id.to_string()));
+            },
+        }
+
+        let submission_json: Value = client.post_response(ApiEndpoint::Submissions(course_id, assignment_id), &form).await?;
+        Submission::get_submission(submission_json)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{StubResponse, StubServer};
+    use serde_json::json;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    const USER_PATH: &str = "/api/v1/users/self";
+    const USER_JSON: &str = r#"{"id": 1, "name": "Tester"}"#;
+
+    // answers with `first` the first time, and the user after that.
+    async fn start_failing_once(first: impl Fn() -> StubResponse + Send + Sync + 'static) -> StubServer {
+        let count: AtomicUsize = AtomicUsize::new(0);
+        StubServer::start(move |_| {
+            if count.fetch_add(1, Ordering::SeqCst) == 0 { first() } else { StubResponse::new(200, USER_JSON) }
+        }).await
+    }
+
+    fn get_client(server: &StubServer) -> CanvasClient {
+        CanvasClient::new(&server.url, "tok").unwrap().retry_policy(RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_secs(5),
+        })
+    }
+
+    // two pages of numbers, linked with `rel="next"`. The second page is `second`.
+    async fn start_two_pages(second: &'static str) -> StubServer {
+        StubServer::start(move |request| {
+            if request.target.contains("?page=2") {
+                return StubResponse::new(200, second);
+            }
+            let next: String = format!("<http://{}{USER_PATH}?page=2&per_page=2>; rel=\"next\"", request.header("host").unwrap_or_default());
+            StubResponse::new(200, "[1, 2]").header("Link", &format!("<http://example.edu/first>; rel=\"first\", {next}"))
+        }).await
+    }
+
+    #[tokio::test]
+    async fn pages_are_merged() {
+        let server: StubServer = start_two_pages("[3]").await;
+        let client: CanvasClient = get_client(&server).per_page(2);
+
+        let items: Value = client.get_paginated_response(ApiEndpoint::UserSelf).await.unwrap();
+        assert_eq!(items, json!([1, 2, 3]));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].target.contains("per_page=2"));
+        assert!(requests[1].target.contains("?page=2"));
+    }
+
+    #[tokio::test]
+    async fn later_page_that_isnt_a_list_fails() {
+        let server: StubServer = start_two_pages(r#"{"errors": []}"#).await;
+        let result: Result<Value> = get_client(&server).get_paginated_response(ApiEndpoint::UserSelf).await;
+        assert!(matches!(result, Err(Error::Json { .. })));
+    }
+
+    #[tokio::test]
+    async fn unavailable_is_retried() {
+        let server: StubServer = start_failing_once(|| StubResponse::new(503, "")).await;
+        let user: User = User::get_self(&get_client(&server)).await.unwrap();
+        assert_eq!(user.name, "Tester");
+        assert_eq!(server.count(USER_PATH), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_after_seconds_is_waited() {
+        let server: StubServer = start_failing_once(|| StubResponse::new(429, "").header("Retry-After", "1")).await;
+        let start: Instant = Instant::now();
+        User::get_self(&get_client(&server)).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.count(USER_PATH), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_after_date_is_waited() {
+        let server: StubServer = start_failing_once(|| {
+            let date: String = (Utc::now() + chrono::Duration::seconds(2)).format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+            StubResponse::new(503, "").header("Retry-After", &date)
+        }).await;
+        let start: Instant = Instant::now();
+        User::get_self(&get_client(&server)).await.unwrap();
+        // the date only has whole seconds.
+        assert!(start.elapsed() >= Duration::from_millis(900));
+        assert_eq!(server.count(USER_PATH), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_after_longer_than_max_delay_fails() {
+        let server: StubServer = start_failing_once(|| StubResponse::new(503, "").header("Retry-After", "60")).await;
+        let result: Result<User> = User::get_self(&get_client(&server)).await;
+        assert!(matches!(result, Err(Error::Status { status: StatusCode::SERVICE_UNAVAILABLE, .. })));
+        assert_eq!(server.count(USER_PATH), 1);
+    }
+
+    #[tokio::test]
+    async fn throttled_403_is_retried() {
+        let server: StubServer = start_failing_once(|| StubResponse::new(403, "").header("X-Rate-Limit-Remaining", "0")).await;
+        let start: Instant = Instant::now();
+        User::get_self(&get_client(&server)).await.unwrap();
+        // the empty bucket also spaces out the retry.
+        assert!(start.elapsed() >= CanvasClient::RATE_LIMIT_PAUSE);
+        assert_eq!(server.count(USER_PATH), 2);
+    }
+
+    #[tokio::test]
+    async fn forbidden_403_isnt_retried() {
+        let server: StubServer = start_failing_once(|| StubResponse::new(403, "").header("X-Rate-Limit-Remaining", "500")).await;
+        let result: Result<User> = User::get_self(&get_client(&server)).await;
+        assert!(matches!(result, Err(Error::Status { status: StatusCode::FORBIDDEN, .. })));
+        assert_eq!(server.count(USER_PATH), 1);
+    }
+
+    #[tokio::test]
+    async fn post_isnt_retried() {
+        let server: StubServer = start_failing_once(|| StubResponse::new(503, "")).await;
+        let form: [(&str, String); 1] = [("submission[body]", String::from("answers"))];
+        let result: Result<Value> = get_client(&server).post_response(ApiEndpoint::Submissions(1, 2), &form).await;
+
+        assert!(matches!(result, Err(Error::Status { status: StatusCode::SERVICE_UNAVAILABLE, .. })));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+    }
+
+    #[test]
+    fn upload_needs_markdown_to_be_allowed() {
+        let get_types = |extensions: Value| {
+            let assignment: Assignment = serde_json::from_value(json!({
+                "id": 1, "name": "Lab 1",
+                "submission_types": ["online_text_entry", "online_upload"],
+                "allowed_extensions": extensions,
+            })).unwrap();
+            SubmissionType::get_supported_types(&assignment)
+        };
+        assert!(get_types(json!([])) == vec!{SubmissionType::OnlineTextEntry, SubmissionType::OnlineUpload});
+        assert!(get_types(json!(["pdf", "MD"])) == vec!{SubmissionType::OnlineTextEntry, SubmissionType::OnlineUpload});
+        assert!(get_types(json!(["pdf"])) == vec!{SubmissionType::OnlineTextEntry});
+    }
+
+    #[test]
+    fn odd_assignments_dont_hide_the_rest() {
+        let assignments: Vec<Assignment> = Assignment::get_all_assignments(json!([
+            {"id": 1, "name": "Lab 1", "rubric": [{"id": "r1", "ratings": [{"points": 1.0}]}]},
+            {"id": 99999999999_i64, "name": "Lab 1.5", "assignment_group_id": 88888888888_i64},
+            {"id": "3", "name": "Not a number"},
+            {"name": "No id"},
+            {"id": 2, "name": "Lab 2", "submission": {"submitted_at": null}},
+        ])).unwrap();
+
+        let names: Vec<&str> = assignments.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, vec!{"Lab 1", "Lab 1.5", "Lab 2"});
+        assert_eq!(assignments[1].id, 99999999999);
+        assert_eq!(assignments[1].assignment_group_id, Some(88888888888));
+        assert_eq!(assignments[0].rubric[0].description, "");
+        assert_eq!(assignments[0].rubric[0].points, 0.0);
+    }
+
+    #[test]
+    fn odd_courses_dont_hide_the_rest() {
+        let courses: Vec<Course> = Course::get_all_courses(json!([
+            {"id": 1, "name": "COS 120", "term": {"id": 77777777777_i64, "name": "Fall 2026"}},
+            {"id": 2, "access_restricted_by_date": true},
+            {"id": 3},
+            {"id": 99999999999_i64, "name": "COS 121"},
+        ]));
+
+        let ids: Vec<i64> = courses.iter().map(|x| x.id).collect();
+        assert_eq!(ids, vec!{1, 99999999999});
+        assert_eq!(courses[0].term.as_ref().unwrap().id, 77777777777);
+    }
+
+    fn get_cache(name: &str) -> ResponseCache {
+        let dir: PathBuf = std::env::temp_dir().join(format!("maker-requester-test-{}-{name}", std::process::id()));
+        ResponseCache::new(dir)
+    }
+
+    #[tokio::test]
+    async fn unchanged_response_is_read_from_the_cache() {
+        let server: StubServer = StubServer::start(|request| match request.header("if-none-match") {
+            Some("\"v1\"") => StubResponse::new(304, ""),
+            _ => StubResponse::new(200, USER_JSON).header("ETag", "\"v1\""),
+        }).await;
+        let cache: ResponseCache = get_cache("etag");
+        let dir: PathBuf = cache.dir().to_path_buf();
+        let client: CanvasClient = get_client(&server).with_cache(cache);
+
+        assert_eq!(User::get_self(&client).await.unwrap().name, "Tester");
+        assert_eq!(User::get_self(&client).await.unwrap().name, "Tester");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("if-none-match"), None);
+        assert_eq!(requests[1].header("if-none-match"), Some("\"v1\""));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cached_pages_are_read_offline() {
+        let server: StubServer = start_two_pages("[3]").await;
+        let cache: ResponseCache = get_cache("offline");
+        let dir: PathBuf = cache.dir().to_path_buf();
+        let client: CanvasClient = get_client(&server).per_page(2).with_cache(cache);
+        client.get_paginated_response(ApiEndpoint::UserSelf).await.unwrap();
+
+        // the second page is found through the cached first page's next link.
+        let offline: CanvasClient = get_client(&server).per_page(2).with_cache(ResponseCache::new(&dir)).offline(true);
+        let items: Value = offline.get_paginated_response(ApiEndpoint::UserSelf).await.unwrap();
+        assert_eq!(items, json!([1, 2, 3]));
+        assert_eq!(server.requests().len(), 2);
+
+        let result: Result<Value> = offline.get_paginated_response(ApiEndpoint::CourseList(false)).await;
+        assert!(matches!(result, Err(Error::Offline(_))));
+        assert_eq!(server.requests().len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
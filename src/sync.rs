@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::requester::Assignment;
+
+/// The state file's name, in `base_dir` next to the lab notes it describes.
+pub const STATE_FILE: &str = ".maker-sync.json";
+
+/// What `maker sync` saw of an assignment the last time.
+#[derive(Serialize, Deserialize)]
+pub struct AssignmentState {
+    pub course_id: i64,
+    pub name: String,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// A hash of the description, to tell a changed description from other edits.
+    pub description_hash: Option<String>,
+}
+
+/// How an assignment differs from what was seen last time.
+#[derive(PartialEq, Eq)]
+pub enum AssignmentChange {
+    /// Never seen before.
+    New,
+    /// The description is different.
+    DescriptionChanged,
+    /// Nothing, or nothing that matters to the lab note.
+    Unchanged,
+}
+
+/// The assignments `maker sync` has already seen, keyed by id.
+#[derive(Serialize, Deserialize, Default)]
+pub struct SyncState {
+    #[serde(default)]
+    assignments: HashMap<i64, AssignmentState>,
+}
+
+fn get_description_hash(assignment: &Assignment) -> Option<String> {
+    let description: &str = assignment.description.as_deref()?;
+    Some(Sha256::digest(description.as_bytes()).iter().map(|x| format!("{x:02x}")).collect())
+}
+
+impl SyncState {
+    /// The state file for the lab notes under `base_dir`.
+    pub fn get_state_file(base_dir: &Path) -> PathBuf {
+        base_dir.join(STATE_FILE)
+    }
+
+    /// Reads the state file, an empty state if there isn't one yet.
+    pub fn load(path: &Path) -> Result<SyncState> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|error| Error::json("the sync state file", error)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(SyncState::default()),
+            Err(error) => Err(Error::io(path, error)),
+        }
+    }
+
+    /// Writes the state file.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|error| Error::io(dir, error))?;
+        }
+        let contents: String = serde_json::to_string_pretty(self).map_err(|error| Error::json("the sync state file", error))?;
+        fs::write(path, contents).map_err(|error| Error::io(path, error))
+    }
+
+    /// Compares an assignment with what was seen last time. `updated_at` is
+    /// checked first, so only edited assignments have their description compared.
+    pub fn get_change(&self, assignment: &Assignment) -> AssignmentChange {
+        let Some(state) = self.assignments.get(&assignment.id) else {
+            return AssignmentChange::New;
+        };
+        if state.updated_at == assignment.updated_at || state.description_hash == get_description_hash(assignment) {
+            AssignmentChange::Unchanged
+        } else {
+            AssignmentChange::DescriptionChanged
+        }
+    }
+
+    /// Remembers the assignment as it is now.
+    pub fn update(&mut self, course_id: i64, assignment: &Assignment) {
+        self.assignments.insert(assignment.id, AssignmentState {
+            course_id,
+            name: assignment.name.clone(),
+            updated_at: assignment.updated_at,
+            description_hash: get_description_hash(assignment),
+        });
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::{DateTime, Local, Utc};
+use minijinja::Environment;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::parser::ParsedDescription;
+use crate::requester::{Assignment, Course, RubricCriterion};
+
+/// The layout used when no template is configured.
+///
+/// Templates use [minijinja](https://docs.rs/minijinja) (Jinja2) syntax and
+/// get these variables:
+///
+/// - `course.id`, `course.name`, `course.code`
+/// - `assignment.id`, `assignment.name`, `assignment.html_url`
+/// - `assignment.due_at`, `assignment.lock_at`, `assignment.unlock_at` (RFC 3339
+///   in UTC), `assignment.due_date` (local time)
+/// - `assignment.points` (like `10`), `assignment.points_possible` (a number)
+/// - `assignment.submission_types`, `assignment.allowed_extensions`, lists of strings
+/// - `assignment.rubric`, a list of criteria with `description`,
+///   `long_description`, `points` and `ratings` (each with `description` and `points`)
+/// - `author`, the name from the config
+/// - `generated_date`, today's date
+/// - `questions`, a list of markdown strings without their number
+/// - `description`, the whole description as markdown
+/// - `show_description`, true with `--full` or `full_description`
+pub const DEFAULT_TEMPLATE: &str = "# {{ assignment.name }}
+#### _By {{ author }}_
+
+{% if show_description and description %}## Instructions
+
+{{ description }}
+{% endif %}{% if questions %}
+## Questions
+{% for question in questions %}
+{{ loop.index }}. {{ question }}
+
+{% endfor %}{% endif %}";
+
+const TEMPLATE_NAME: &str = "lab_note.md";
+
+#[derive(Serialize)]
+struct CourseContext<'a> {
+    id: i64,
+    name: &'a str,
+    code: &'a str,
+}
+
+#[derive(Serialize)]
+struct AssignmentContext<'a> {
+    id: i64,
+    name: &'a str,
+    due_at: Option<DateTime<Utc>>,
+    due_date: Option<String>,
+    lock_at: Option<DateTime<Utc>>,
+    unlock_at: Option<DateTime<Utc>>,
+    points: Option<String>,
+    points_possible: Option<f64>,
+    submission_types: &'a [String],
+    allowed_extensions: &'a [String],
+    html_url: Option<&'a str>,
+    rubric: &'a [RubricCriterion],
+}
+
+/// Everything a template can use.
+#[derive(Serialize)]
+pub struct NoteContext<'a> {
+    course: CourseContext<'a>,
+    assignment: AssignmentContext<'a>,
+    author: &'a str,
+    generated_date: String,
+    questions: &'a [String],
+    description: &'a str,
+    show_description: bool,
+}
+
+impl<'a> NoteContext<'a> {
+    /// Gathers the variables for one assignment's lab note.
+    pub fn new(course: &'a Course, assignment: &'a Assignment, parsed: &'a ParsedDescription, author: &'a str, show_description: bool) -> NoteContext<'a> {
+        NoteContext {
+            course: CourseContext { id: course.id, name: &course.name, code: &course.course_code },
+            assignment: AssignmentContext {
+                id: assignment.id,
+                name: &assignment.name,
+                due_at: assignment.due_at,
+                due_date: assignment.get_local_due_date(),
+                lock_at: assignment.lock_at,
+                unlock_at: assignment.unlock_at,
+                points: assignment.get_points(),
+                points_possible: assignment.points_possible,
+                submission_types: &assignment.submission_types,
+                allowed_extensions: &assignment.allowed_extensions,
+                html_url: assignment.html_url.as_deref(),
+                rubric: &assignment.rubric,
+            },
+            author,
+            generated_date: Local::now().format("%Y-%m-%d").to_string(),
+            questions: &parsed.questions,
+            description: &parsed.description,
+            show_description,
+        }
+    }
+}
+
+/// Reads a template file.
+pub fn load_template(path: &Path) -> Result<String> {
+    fs::read_to_string(path).map_err(|error| Error::io(path, error))
+}
+
+/// Renders a lab note from a template.
+pub fn render(template: &str, context: &NoteContext) -> Result<String> {
+    let mut env: Environment = Environment::new();
+    // a markdown file, so keep the file's last newline.
+    env.set_keep_trailing_newline(true);
+    env.add_template(TEMPLATE_NAME, template).map_err(|error| Error::Template(error.to_string()))?;
+
+    env.get_template(TEMPLATE_NAME)
+        .and_then(|x| x.render(context))
+        .map_err(|error| Error::Template(error.to_string()))
+}
@@ -0,0 +1,125 @@
+//! A tiny HTTP server on localhost for tests, answering every request with
+//! whatever the handler returns and remembering what it was sent.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+/// A request the server got.
+#[derive(Clone)]
+pub(crate) struct StubRequest {
+    pub(crate) method: String,
+    /// The path with its query, like `/api/v1/users/self?per_page=50`.
+    pub(crate) target: String,
+    /// Header names are lowercase.
+    pub(crate) headers: HashMap<String, String>,
+    pub(crate) body: String,
+}
+
+impl StubRequest {
+    pub(crate) fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|x| x.as_str())
+    }
+}
+
+/// What the server answers with.
+pub(crate) struct StubResponse {
+    pub(crate) status: u16,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: String,
+}
+
+impl StubResponse {
+    pub(crate) fn new(status: u16, body: &str) -> StubResponse {
+        StubResponse { status, headers: Vec::new(), body: body.to_string() }
+    }
+
+    pub(crate) fn header(mut self, name: &str, value: &str) -> StubResponse {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// A running server, stopped when the test's runtime is.
+pub(crate) struct StubServer {
+    pub(crate) url: String,
+    requests: Arc<Mutex<Vec<StubRequest>>>,
+}
+
+impl StubServer {
+    /// Starts a server on a free port.
+    pub(crate) async fn start(handler: impl Fn(&StubRequest) -> StubResponse + Send + Sync + 'static) -> StubServer {
+        let listener: TcpListener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url: String = format!("http://{}", listener.local_addr().unwrap());
+        let requests: Arc<Mutex<Vec<StubRequest>>> = Arc::new(Mutex::new(Vec::new()));
+        let handler = Arc::new(handler);
+
+        let received: Arc<Mutex<Vec<StubRequest>>> = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let received: Arc<Mutex<Vec<StubRequest>>> = received.clone();
+                tokio::spawn(async move {
+                    let mut reader: BufReader<_> = BufReader::new(stream);
+                    let Some(request) = read_request(&mut reader).await else {
+                        return;
+                    };
+                    received.lock().unwrap().push(request.clone());
+                    let response: StubResponse = handler(&request);
+
+                    let mut head: String = format!("HTTP/1.1 {} Stub\r\nContent-Length: {}\r\nConnection: close\r\n", response.status, response.body.len());
+                    for (name, value) in response.headers.iter() {
+                        head.push_str(&format!("{name}: {value}\r\n"));
+                    }
+                    head.push_str("\r\n");
+                    // the client may have given up already.
+                    let _ = reader.get_mut().write_all(format!("{head}{}", response.body).as_bytes()).await;
+                    let _ = reader.get_mut().shutdown().await;
+                });
+            }
+        });
+
+        StubServer { url, requests }
+    }
+
+    /// Every request so far, in the order they came in.
+    pub(crate) fn requests(&self) -> Vec<StubRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// How many requests were sent to `path`.
+    pub(crate) fn count(&self, path: &str) -> usize {
+        self.requests().iter().filter(|x| x.path() == path).count()
+    }
+}
+
+async fn read_request(reader: &mut BufReader<tokio::net::TcpStream>) -> Option<StubRequest> {
+    let mut line: String = String::new();
+    reader.read_line(&mut line).await.ok()?;
+    let mut parts = line.split_whitespace();
+    let method: String = parts.next()?.to_string();
+    let target: String = parts.next()?.to_string();
+
+    let mut headers: HashMap<String, String> = HashMap::new();
+    loop {
+        let mut line: String = String::new();
+        reader.read_line(&mut line).await.ok()?;
+        let line: &str = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length: usize = headers.get("content-length").and_then(|x| x.parse().ok()).unwrap_or(0);
+    let mut body: Vec<u8> = vec![0; length];
+    reader.read_exact(&mut body).await.ok()?;
+    Some(StubRequest { method, target, headers, body: String::from_utf8_lossy(&body).to_string() })
+}